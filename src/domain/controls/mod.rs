@@ -1,4 +1,13 @@
-use crate::domain::simulation::{Body, Player, ResetEvent, SimSettings, SpawnBurst};
+use crate::domain::simulation::capture::ExportCapture;
+use crate::domain::simulation::replay::Replay;
+use crate::domain::simulation::rewind::Rewind;
+use crate::domain::simulation::snapshot::{
+    quicksave_path, LoadSnapshot, SaveSnapshot, SnapshotFormat,
+};
+use crate::domain::simulation::viewer::Viewer;
+use crate::domain::simulation::{
+    AppState, BurstRequest, Player, PlayerInput, ResetEvent, SimPhase, SimSettings, SpawnBurst,
+};
 use crate::MainCamera;
 use bevy::input::mouse::{MouseButtonInput, MouseWheel};
 use bevy::input::ButtonState; // needed in Bevy 0.14
@@ -7,20 +16,39 @@ use bevy::prelude::*;
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(DragState::default()).add_systems(
-            Update,
-            (
-                camera_controls,
-                drag_spawn,
-                player_thrust,
-                pause_toggle,
-                follow_toggle,
-                time_scale_toggle,
-                reset_trigger,
-                help_toggle,
-                diagnostics_toggle,
-            ),
-        );
+        app.insert_resource(DragState::default())
+            .add_systems(
+                Update,
+                (
+                    camera_controls,
+                    follow_toggle,
+                    capture_key,
+                    help_toggle,
+                    diagnostics_toggle,
+                )
+                    .in_set(SimPhase::Input),
+            )
+            // The replay viewer only re-executes the replay.
+            .add_systems(
+                Update,
+                (
+                    drag_spawn,
+                    player_thrust,
+                    pause_toggle,
+                    time_scale_toggle,
+                    reset_trigger,
+                    quicksave_keys,
+                    rewind_key,
+                )
+                    .in_set(SimPhase::Input)
+                    .run_if(not(in_state(AppState::Viewing))),
+            )
+            .add_systems(
+                Update,
+                viewer_keys
+                    .in_set(SimPhase::Input)
+                    .run_if(in_state(AppState::Viewing)),
+            );
     }
 }
 
@@ -103,8 +131,7 @@ fn drag_spawn(
     mut drag: ResMut<DragState>,
     mut mousebtn_evr: EventReader<MouseButtonInput>,
     buttons: Res<ButtonInput<MouseButton>>,
-    mut ev_spawn: EventWriter<SpawnBurst>,
-    _settings: Res<SimSettings>,
+    mut ev_burst: EventWriter<BurstRequest>,
 ) {
     let win = windows.single();
     let Some(cursor) = win.cursor_position() else {
@@ -123,13 +150,13 @@ fn drag_spawn(
             ButtonState::Released if ev.button == MouseButton::Left => {
                 if let Some(s) = drag.start.take() {
                     let radius = (world - s).length().max(10.0);
-                    ev_spawn.send(SpawnBurst {
+                    ev_burst.send(BurstRequest(SpawnBurst {
                         center: s,
                         radius,
                         count: (radius * 0.8) as usize,
                         base_mass: 20.0,
                         speed: 120.0,
-                    });
+                    }));
                 }
                 drag.button = None;
             }
@@ -142,38 +169,25 @@ fn drag_spawn(
     }
 }
 
-fn player_thrust(
-    time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut players: Query<&mut Body, With<Player>>,
-) {
-    let dt = time.delta_seconds();
-    if let Ok(mut player_body) = players.get_single_mut() {
-        let mut dir = Vec2::ZERO;
+/// Thrust is applied by the simulation at the start of each tick.
+fn player_thrust(keys: Res<ButtonInput<KeyCode>>, mut input: ResMut<PlayerInput>) {
+    let mut dir = Vec2::ZERO;
 
-        if keys.pressed(KeyCode::ArrowUp) || keys.pressed(KeyCode::KeyW) {
-            dir.y += 1.0;
-        }
-        if keys.pressed(KeyCode::ArrowDown) || keys.pressed(KeyCode::KeyS) {
-            dir.y -= 1.0;
-        }
-        if keys.pressed(KeyCode::ArrowLeft) || keys.pressed(KeyCode::KeyA) {
-            dir.x -= 1.0;
-        }
-        if keys.pressed(KeyCode::ArrowRight) || keys.pressed(KeyCode::KeyD) {
-            dir.x += 1.0;
-        }
-
-        if dir != Vec2::ZERO {
-            let boost = if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) {
-                1.75
-            } else {
-                1.0
-            };
-            let acc = dir.normalize() * 380.0 * boost / player_body.mass.max(1.0);
-            player_body.vel += acc * dt;
-        }
+    if keys.pressed(KeyCode::ArrowUp) || keys.pressed(KeyCode::KeyW) {
+        dir.y += 1.0;
     }
+    if keys.pressed(KeyCode::ArrowDown) || keys.pressed(KeyCode::KeyS) {
+        dir.y -= 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowLeft) || keys.pressed(KeyCode::KeyA) {
+        dir.x -= 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowRight) || keys.pressed(KeyCode::KeyD) {
+        dir.x += 1.0;
+    }
+
+    let boost = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    input.set_if_neq(PlayerInput { thrust: dir, boost });
 }
 
 fn pause_toggle(mut settings: ResMut<SimSettings>, keys: Res<ButtonInput<KeyCode>>) {
@@ -200,7 +214,65 @@ fn time_scale_toggle(mut settings: ResMut<SimSettings>, keys: Res<ButtonInput<Ke
 
 fn reset_trigger(mut ev_reset: EventWriter<ResetEvent>, keys: Res<ButtonInput<KeyCode>>) {
     if keys.just_pressed(KeyCode::KeyR) {
-        ev_reset.send(ResetEvent::default());
+        ev_reset.send(ResetEvent);
+    }
+}
+
+/// F5 quicksaves and F9 quickloads; with Shift held they use the binary format.
+fn quicksave_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut ev_save: EventWriter<SaveSnapshot>,
+    mut ev_load: EventWriter<LoadSnapshot>,
+) {
+    let format = if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) {
+        SnapshotFormat::Binary
+    } else {
+        SnapshotFormat::Ron
+    };
+    if keys.just_pressed(KeyCode::F5) {
+        ev_save.send(SaveSnapshot {
+            path: quicksave_path(format),
+            format,
+        });
+    }
+    if keys.just_pressed(KeyCode::F9) {
+        ev_load.send(LoadSnapshot {
+            path: quicksave_path(format),
+        });
+    }
+}
+
+/// F7 exports the capture buffer as JSON; with Shift held, a CSV as well.
+fn capture_key(keys: Res<ButtonInput<KeyCode>>, mut ev_export: EventWriter<ExportCapture>) {
+    if keys.just_pressed(KeyCode::F7) {
+        ev_export.send(ExportCapture {
+            csv: keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight),
+        });
+    }
+}
+
+/// Holding Z rewinds the simulation.
+fn rewind_key(keys: Res<ButtonInput<KeyCode>>, mut rewind: ResMut<Rewind>) {
+    let held = keys.pressed(KeyCode::KeyZ);
+    if rewind.held != held {
+        rewind.held = held;
+    }
+}
+
+/// Space plays and pauses the replay viewer; comma and period step a tick.
+fn viewer_keys(keys: Res<ButtonInput<KeyCode>>, replay: Res<Replay>, mut viewer: ResMut<Viewer>) {
+    if keys.just_pressed(KeyCode::Space) {
+        viewer.paused = !viewer.paused;
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        viewer.paused = true;
+        viewer.steps += 1;
+    }
+    if keys.just_pressed(KeyCode::Comma) {
+        if let Some(pb) = replay.playback() {
+            viewer.paused = true;
+            viewer.seek = Some(pb.tick.saturating_sub(1));
+        }
     }
 }
 
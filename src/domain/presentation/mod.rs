@@ -4,17 +4,151 @@ use bevy::diagnostic::{
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 
+use crate::domain::simulation::accretion::AccretionStats;
+use crate::domain::simulation::autosave::Autosave;
+use crate::domain::simulation::capture::{CaptureBuffer, ExportCapture};
+use crate::domain::simulation::crash::CrashReport;
+use crate::domain::simulation::fields::ExternalField;
+use crate::domain::simulation::force_law::{ActiveForceLaw, ForceLawKind};
+use crate::domain::simulation::galaxy::HaloModel;
+use crate::domain::simulation::ghost::{Ghost, GhostCommand};
+use crate::domain::simulation::orbits::{self, Orbits};
+use crate::domain::simulation::post_newtonian::BlackHoleMerger;
+use crate::domain::simulation::regularization::RegularizedPairs;
+use crate::domain::simulation::replay::{self, Replay, ReplayCommand, ReplayHeader, ReplayMode};
+use crate::domain::simulation::rewind::Rewind;
+use crate::domain::simulation::satellites::{
+    Satellite, SatelliteCaptured, SatelliteLossReason, SatelliteLost,
+};
+use crate::domain::simulation::snapshot::{
+    quicksave_path, LoadSnapshot, SaveSnapshot, SnapshotFormat, SnapshotStatus,
+};
+use crate::domain::simulation::state_hash::StateHash;
+use crate::domain::simulation::structure::Structures;
+use crate::domain::simulation::viewer::{self, MarkerKind, Viewer};
 use crate::domain::simulation::{
     AppState, Body, CollisionMode, ColorPalette, Mission, Objective, Player, ResetEvent, Scenario,
-    SimSettings, SimState, SimStats, SystemType,
+    SimClock, SimPhase, SimSettings, SimState, SimStats, SystemType,
 };
+use std::path::PathBuf;
+
+/// What "Add" in the external fields list offers, centred on the origin.
+const FIELD_TEMPLATES: [ExternalField; 6] = [
+    ExternalField::PointMass {
+        pos: Vec2::new(1500.0, 0.0),
+        mass: 2e5,
+        softening: 50.0,
+    },
+    ExternalField::Uniform {
+        acc: Vec2::new(0.0, -20.0),
+    },
+    ExternalField::LogarithmicHalo {
+        centre: Vec2::ZERO,
+        v0: 300.0,
+        core_radius: 500.0,
+        flattening: 0.9,
+    },
+    ExternalField::Halo {
+        centre: Vec2::ZERO,
+        model: HaloModel::Nfw {
+            mass: 1e7,
+            scale_radius: 3000.0,
+        },
+    },
+    ExternalField::RotatingBar {
+        centre: Vec2::ZERO,
+        mass: 2e5,
+        half_length: 400.0,
+        pattern_speed: 0.3,
+        phase: 0.0,
+        softening: 50.0,
+    },
+    ExternalField::Tidal {
+        centre: Vec2::ZERO,
+        host_mass: 5e7,
+        distance: 20000.0,
+        angle: 0.0,
+        angular_speed: 0.02,
+    },
+];
 
 pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EguiPlugin)
-            .add_systems(Update, ui_system.run_if(in_state(AppState::Playing)))
-            .add_systems(Update, game_over_ui.run_if(in_state(AppState::GameOver)));
+            .init_resource::<EventFeed>()
+            .add_systems(
+                Update,
+                (
+                    record_events,
+                    ui_system,
+                    snapshot_ui,
+                    replay_ui,
+                    capture_ui,
+                    ghost_ui,
+                    crash_ui,
+                    continue_ui,
+                )
+                    .chain()
+                    .in_set(SimPhase::Input)
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(Update, game_over_ui.run_if(in_state(AppState::GameOver)))
+            .add_systems(
+                Update,
+                viewer_ui
+                    .in_set(SimPhase::Input)
+                    .run_if(in_state(AppState::Viewing)),
+            );
+    }
+}
+
+/// Notable simulation events, kept for the HUD after their frame has passed.
+#[derive(Resource, Default)]
+struct EventFeed {
+    last_merger: Option<BlackHoleMerger>,
+    moon_message: Option<String>,
+}
+
+fn record_events(
+    mut feed: ResMut<EventFeed>,
+    mut mergers: EventReader<BlackHoleMerger>,
+    mut captured: EventReader<SatelliteCaptured>,
+    mut lost: EventReader<SatelliteLost>,
+    player_q: Query<Entity, With<Player>>,
+    body_q: Query<&Body>,
+) {
+    if let Some(m) = mergers.read().last() {
+        feed.last_merger = Some(*m);
+    }
+    let player = player_q.get_single().ok();
+    let moon_or_submoon = |parent: Entity| {
+        if Some(parent) == player {
+            "moon"
+        } else {
+            "sub-moon"
+        }
+    };
+    for ev in captured.read() {
+        let class = body_q.get(ev.satellite).map(|b| b.class).ok();
+        feed.moon_message = Some(match class {
+            Some(class) => format!("Captured a {:?} {}!", class, moon_or_submoon(ev.parent)),
+            None => format!("Captured a {}!", moon_or_submoon(ev.parent)),
+        });
+    }
+    for ev in lost.read() {
+        feed.moon_message = Some(match ev.reason {
+            SatelliteLossReason::Escaped => match body_q.get(ev.satellite) {
+                Ok(b) => format!("A {:?} {} escaped.", b.class, moon_or_submoon(ev.parent)),
+                Err(_) => format!("A {} escaped.", moon_or_submoon(ev.parent)),
+            },
+            SatelliteLossReason::Collided => {
+                format!(
+                    "A {} was destroyed in a collision.",
+                    moon_or_submoon(ev.parent)
+                )
+            }
+        });
     }
 }
 
@@ -22,10 +156,19 @@ fn ui_system(
     mut contexts: EguiContexts,
     mut settings: ResMut<SimSettings>,
     stats: Res<SimStats>,
-    player_q: Query<(&Body, &Player)>,
+    player_q: Query<(Entity, &Body, &Player)>,
+    body_q: Query<&Body>,
     mut next_state: ResMut<NextState<SimState>>,
     diagnostics: Res<DiagnosticsStore>,
     mission: Res<Mission>,
+    orbits: Res<Orbits>,
+    force_law: Res<ActiveForceLaw>,
+    accretion: Res<AccretionStats>,
+    structures: Res<Structures>,
+    pairs: Res<RegularizedPairs>,
+    state_hash: Res<StateHash>,
+    feed: Res<EventFeed>,
+    satellite_q: Query<&Satellite>,
 ) {
     egui::Window::new("Settings").show(contexts.ctx_mut(), |ui| {
         ui.label(format!("Bodies: {}", stats.0));
@@ -35,24 +178,83 @@ fn ui_system(
             }
         }
         ui.label(format!("Sim Rate: {:.2}x", settings.time_scale));
-        if let Ok((body, player)) = player_q.get_single() {
+        if accretion.total > 0.0 {
+            ui.label(format!(
+                "BH Accretion: {:.1}/s  (total {:.0})",
+                accretion.rate, accretion.total
+            ));
+        }
+        if let Ok((player_entity, body, player)) = player_q.get_single() {
             ui.label(format!(
                 "Player — Mass: {:.1}  Class: {:?}  Score: {:.0}",
                 body.mass, body.class, player.score
             ));
+            if let Some(orbit) = orbits.get(player_entity) {
+                let attractor_class = body_q.get(orbit.attractor).ok().map(|b| b.class);
+                let el = &orbit.elements;
+                match (el.period, el.apoapsis) {
+                    (Some(period), Some(apo)) => ui.label(format!(
+                        "Orbit: {}  a={:.0}  e={:.2}  ω={:.0}°  T={:.1}s  q={:.0}  Q={:.0}",
+                        orbits::describe(orbit, attractor_class),
+                        el.semi_major_axis,
+                        el.eccentricity,
+                        el.argument_of_periapsis.to_degrees(),
+                        period,
+                        el.periapsis,
+                        apo
+                    )),
+                    _ => ui.label(format!(
+                        "Orbit: {}  e={:.2}  ω={:.0}°  q={:.0}",
+                        orbits::describe(orbit, attractor_class),
+                        el.eccentricity,
+                        el.argument_of_periapsis.to_degrees(),
+                        el.periapsis
+                    )),
+                };
+            }
+            let bound_to_player = orbits.satellites_of(player_entity).count();
+            if bound_to_player > 0 {
+                ui.label(format!("Bodies bound to player: {}", bound_to_player));
+            }
+            if player.moons > 0 {
+                let sub_moons = satellite_q
+                    .iter()
+                    .filter(|s| s.parent != player_entity)
+                    .count();
+                ui.label(format!(
+                    "Moons: {}  (sub-moons: {})",
+                    player.moons, sub_moons
+                ));
+            }
+            if let Some(msg) = &feed.moon_message {
+                ui.label(msg.as_str());
+            }
+            if let Some(group) = structures.group_of(player_entity) {
+                ui.label(format!(
+                    "Player group #{}: {} members, {:?}",
+                    group.id,
+                    group.members.len(),
+                    group.shape
+                ));
+            }
+            if let Some(system) = structures.subsystem_of(player_entity) {
+                ui.label(format!(
+                    "Player system #{}: {} satellites, mass {:.0}",
+                    system.id,
+                    system.satellites.len(),
+                    system.mass
+                ));
+            }
         }
 
         ui.separator();
 
         if !mission.completed {
-            match mission.objective {
-                Objective::Survive => {
-                    ui.label(format!(
-                        "Survive: {:.0} / {:.0}s",
-                        mission.progress, mission.goal
-                    ));
-                }
-                _ => {}
+            if mission.objective == Objective::Survive {
+                ui.label(format!(
+                    "Survive: {:.0} / {:.0}s",
+                    mission.progress, mission.goal
+                ));
             }
         } else {
             ui.label("Mission Completed!");
@@ -81,6 +283,11 @@ fn ui_system(
                     "Star Nursery",
                 );
                 ui.selectable_value(&mut settings.scenario, Scenario::BHArena, "BH Arena");
+                ui.selectable_value(
+                    &mut settings.scenario,
+                    Scenario::SpiralGalaxy,
+                    "Spiral Galaxy",
+                );
             });
 
         ui.separator();
@@ -99,6 +306,7 @@ fn ui_system(
                     "Binary Star",
                 );
                 ui.selectable_value(&mut settings.system_type, SystemType::Cluster, "Cluster");
+                ui.selectable_value(&mut settings.system_type, SystemType::Galaxy, "Galaxy");
             });
 
         ui.separator();
@@ -121,6 +329,93 @@ fn ui_system(
 
         ui.separator();
 
+        let law_name = match settings.force_law {
+            ForceLawKind::Newtonian => "Newtonian",
+            ForceLawKind::PlummerSoftened { .. } => "Plummer-softened",
+            ForceLawKind::Yukawa { .. } => "Yukawa",
+            ForceLawKind::Mond { .. } => "MOND",
+            ForceLawKind::Custom => "Custom",
+        };
+        egui::ComboBox::from_label("Force Law")
+            .selected_text(law_name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut settings.force_law,
+                    ForceLawKind::Newtonian,
+                    "Newtonian",
+                );
+                if ui
+                    .selectable_label(
+                        matches!(settings.force_law, ForceLawKind::PlummerSoftened { .. }),
+                        "Plummer-softened",
+                    )
+                    .clicked()
+                {
+                    settings.force_law = ForceLawKind::PlummerSoftened { scale: 30.0 };
+                }
+                if ui
+                    .selectable_label(
+                        matches!(settings.force_law, ForceLawKind::Yukawa { .. }),
+                        "Yukawa",
+                    )
+                    .clicked()
+                {
+                    settings.force_law = ForceLawKind::Yukawa { length: 800.0 };
+                }
+                if ui
+                    .selectable_label(
+                        matches!(settings.force_law, ForceLawKind::Mond { .. }),
+                        "MOND",
+                    )
+                    .clicked()
+                {
+                    settings.force_law = ForceLawKind::Mond { a0: 20.0 };
+                }
+                ui.selectable_value(&mut settings.force_law, ForceLawKind::Custom, "Custom");
+            });
+        match &mut settings.force_law {
+            ForceLawKind::PlummerSoftened { scale } => {
+                ui.add(egui::Slider::new(scale, 0.0..=200.0).text("Core Radius"));
+            }
+            ForceLawKind::Yukawa { length } => {
+                ui.add(egui::Slider::new(length, 50.0..=5000.0).text("Screening Length"));
+            }
+            ForceLawKind::Mond { a0 } => {
+                ui.add(egui::Slider::new(a0, 0.1..=200.0).text("a0"));
+            }
+            ForceLawKind::Newtonian | ForceLawKind::Custom => {}
+        }
+
+        egui::CollapsingHeader::new(format!(
+            "External Fields ({})",
+            settings.external_fields.len()
+        ))
+        .show(ui, |ui| {
+            let mut remove = None;
+            for (i, field) in settings.external_fields.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(field.name());
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                settings.external_fields.remove(i);
+            }
+            ui.menu_button("Add", |ui| {
+                for field in FIELD_TEMPLATES {
+                    if ui.button(field.name()).clicked() {
+                        settings.external_fields.push(field);
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.label("Applied on the next reset.");
+        });
+
+        ui.separator();
+
         if ui
             .checkbox(&mut settings.deterministic, "Deterministic")
             .changed()
@@ -131,10 +426,75 @@ fn ui_system(
                 next_state.set(SimState::Parallel);
             }
         }
+        ui.horizontal(|ui| {
+            if settings.deterministic {
+                ui.add(egui::DragValue::new(&mut settings.seed).prefix("Seed: "))
+                    .on_hover_text("Used on the next reset");
+            } else {
+                ui.label(format!("Seed: {}", settings.seed));
+            }
+        });
         ui.checkbox(&mut settings.trails_enabled, "Trails");
 
         ui.separator();
 
+        ui.checkbox(&mut settings.electrostatics, "Electrostatics");
+        if settings.electrostatics {
+            ui.add(egui::Slider::new(&mut settings.coulomb_k, 0.0..=500.0).text("Coulomb k"));
+            ui.add(egui::Slider::new(&mut settings.burst_charge, 0.0..=200.0).text("Burst Charge"));
+        }
+        ui.checkbox(&mut settings.radiation.enabled, "Radiation Pressure");
+        if settings.radiation.enabled {
+            ui.add(
+                egui::Slider::new(&mut settings.radiation.pressure_k, 0.0..=5e6)
+                    .logarithmic(true)
+                    .text("Pressure"),
+            );
+            ui.add(egui::Slider::new(&mut settings.radiation.wind_strip, 0.0..=200.0).text("Wind"));
+        }
+        ui.checkbox(
+            &mut settings.regularization.enabled,
+            "Regularize Close Pairs",
+        );
+        if settings.regularization.enabled {
+            ui.add(
+                egui::Slider::new(&mut settings.regularization.pair_radius, 50.0..=2000.0)
+                    .text("Pair Radius"),
+            );
+        }
+        ui.checkbox(&mut settings.post_newtonian.enabled, "Post-Newtonian BHs");
+        if settings.post_newtonian.enabled {
+            ui.add(
+                egui::Slider::new(&mut settings.post_newtonian.c, 2000.0..=50000.0)
+                    .logarithmic(true)
+                    .text("Speed of Light"),
+            );
+            ui.add(
+                egui::Slider::new(&mut settings.post_newtonian.ripple_strength, 0.0..=5.0)
+                    .text("Merger Ripple"),
+            );
+        }
+        ui.checkbox(&mut settings.accretion.enabled, "BH Accretion Disks");
+        if settings.accretion.enabled {
+            ui.add(
+                egui::Slider::new(&mut settings.accretion.capture_factor, 1.0..=15.0)
+                    .text("Capture Radius"),
+            );
+            ui.add(
+                egui::Slider::new(&mut settings.accretion.viscosity, 0.0..=1.0)
+                    .text("Disk Viscosity"),
+            );
+        }
+        ui.checkbox(&mut settings.sph.enabled, "Gas Dynamics (SPH)");
+        if settings.sph.enabled {
+            ui.checkbox(&mut settings.sph.star_formation, "Star Formation");
+            ui.add(
+                egui::Slider::new(&mut settings.sph.cooling_time, 0.5..=20.0).text("Cooling Time"),
+            );
+        }
+
+        ui.separator();
+
         egui::ComboBox::from_label("Color Palette")
             .selected_text(format!("{:?}", settings.color_palette))
             .show_ui(ui, |ui| {
@@ -185,6 +545,9 @@ fn ui_system(
             ui.label("Space: Pause Simulation");
             ui.label("[/]: Adjust Sim Speed");
             ui.label("R: Reset Simulation");
+            ui.label("F5/F9: Quicksave/Quickload (Shift: binary)");
+            ui.label("F7: Export Capture (Shift: with CSV)");
+            ui.label("Z (hold): Rewind");
             ui.label("H: Toggle Help");
             ui.label("Left Mouse: Spawn Burst (drag)");
             ui.label("Right Mouse: Pan Camera (drag)");
@@ -205,20 +568,552 @@ fn ui_system(
                     ui.label(format!("Entities: {}", value));
                 }
             }
+            ui.label(format!("Tracked orbits: {}", orbits.iter().count()));
+            ui.label(format!("Force law: {}", force_law.law.name()));
+            ui.label(format!("Regularized pairs: {}", pairs.len()));
+            ui.label(format!(
+                "State hash: {:016x} (tick {})",
+                state_hash.value, state_hash.tick
+            ));
+            ui.label(format!(
+                "Groups: {} ({} rings)  Bound subsystems: {}",
+                structures.groups.len(),
+                structures.rings().count(),
+                structures.subsystems.len()
+            ));
+            if let Some(g) = structures.largest_group() {
+                ui.label(format!(
+                    "Largest group #{}: {} members, mass {:.0}, r̄={:.0} at ({:.0}, {:.0}) v={:.0}",
+                    g.id,
+                    g.members.len(),
+                    g.mass,
+                    g.mean_radius,
+                    g.com.x,
+                    g.com.y,
+                    g.vel.length()
+                ));
+            }
+            if let Some(g) = structures.groups.iter().find(|g| !g.merged_from.is_empty()) {
+                let merged: Vec<String> = g.merged_from.iter().map(|id| format!("#{id}")).collect();
+                ui.label(format!("Group #{} took in {}", g.id, merged.join(", ")));
+            }
+            if let Some(s) = structures
+                .subsystems
+                .iter()
+                .max_by(|a, b| a.mass.total_cmp(&b.mass))
+            {
+                ui.label(format!(
+                    "Largest bound system #{}: {:?} with {} satellites, mass {:.0} at ({:.0}, {:.0}) v={:.0}",
+                    s.id,
+                    s.primary_class,
+                    s.satellites.len(),
+                    s.mass,
+                    s.com.x,
+                    s.com.y,
+                    s.vel.length()
+                ));
+            }
+            ui.label(format!(
+                "Tidal disruptions: {}  Accreted: {:.0}",
+                accretion.disruptions, accretion.total
+            ));
+            if let Some(m) = feed.last_merger.as_ref() {
+                ui.label(format!(
+                    "Last BH merger: M={:.3e}  E_gw={:.3e} at ({:.0}, {:.0})",
+                    m.remnant_mass, m.energy_radiated, m.position.x, m.position.y
+                ));
+            }
+        });
+    }
+}
+
+/// Quicksave and quickload buttons, one pair per snapshot format.
+fn snapshot_ui(
+    mut contexts: EguiContexts,
+    status: Res<SnapshotStatus>,
+    mut ev_save: EventWriter<SaveSnapshot>,
+    mut ev_load: EventWriter<LoadSnapshot>,
+    mut rewind: ResMut<Rewind>,
+    mut autosave: ResMut<Autosave>,
+) {
+    egui::Window::new("Snapshots")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (format, name) in [
+                (SnapshotFormat::Ron, "RON"),
+                (SnapshotFormat::Binary, "Binary"),
+            ] {
+                ui.horizontal(|ui| {
+                    let path = quicksave_path(format);
+                    if ui.button(format!("Save {name}")).clicked() {
+                        ev_save.send(SaveSnapshot {
+                            path: path.clone(),
+                            format,
+                        });
+                    }
+                    if ui.button(format!("Load {name}")).clicked() {
+                        ev_load.send(LoadSnapshot { path });
+                    }
+                });
+            }
+            if let Some(message) = &status.message {
+                ui.label(message);
+            }
+
+            ui.separator();
+            ui.checkbox(&mut rewind.enabled, "Rewind (hold Z)");
+            ui.add(egui::Slider::new(&mut rewind.every, 1..=120).text("Keyframe Every N Ticks"));
+            ui.add(
+                egui::Slider::new(&mut rewind.budget_mib, 8.0..=1024.0)
+                    .logarithmic(true)
+                    .text("Memory Budget (MiB)"),
+            );
+            ui.label(format!(
+                "{} keyframes, {:.1} s, {:.1} MiB",
+                rewind.keyframe_count(),
+                rewind.span(),
+                rewind.bytes() as f32 / (1024.0 * 1024.0)
+            ));
+            if rewind.is_scrubbing() {
+                ui.label("Rewinding...");
+            }
+
+            ui.separator();
+            ui.checkbox(&mut autosave.enabled, "Autosave");
+            ui.add(
+                egui::Slider::new(&mut autosave.interval, 5.0..=300.0).text("Every N Sim Seconds"),
+            );
+            ui.add(egui::Slider::new(&mut autosave.slots, 1..=10).text("Slots"));
+            if let Some(message) = &autosave.status {
+                ui.label(message);
+            }
+        });
+}
+
+/// Offers to resume the last session from its newest autosave.
+fn continue_ui(
+    mut contexts: EguiContexts,
+    mut autosave: ResMut<Autosave>,
+    crash: Res<CrashReport>,
+    mut ev_load: EventWriter<LoadSnapshot>,
+) {
+    // The crash offer comes first.
+    if crash.dir.is_some() {
+        return;
+    }
+    let Some(path) = autosave.resume.clone() else {
+        return;
+    };
+    egui::Window::new("Welcome Back")
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Last session: {}", path.display()));
+            ui.horizontal(|ui| {
+                if ui.button("Continue").clicked() {
+                    ev_load.send(LoadSnapshot { path });
+                    autosave.resume = None;
+                }
+                if ui.button("New Game").clicked() {
+                    autosave.resume = None;
+                }
+            });
+        });
+}
+
+/// Settings and export buttons for the rolling capture buffer.
+fn capture_ui(
+    mut contexts: EguiContexts,
+    mut buffer: ResMut<CaptureBuffer>,
+    mut ev_export: EventWriter<ExportCapture>,
+) {
+    egui::Window::new("Capture")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut buffer.enabled, "Capture");
+            ui.add(egui::Slider::new(&mut buffer.seconds, 1.0..=60.0).text("Seconds Kept"));
+            ui.add(egui::Slider::new(&mut buffer.every, 1..=60).text("Every N Ticks"));
+            ui.label(format!(
+                "{} frames, {:.1} s",
+                buffer.frames().len(),
+                buffer.span()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Export JSON").clicked() {
+                    ev_export.send(ExportCapture { csv: false });
+                }
+                if ui.button("Export JSON + CSV").clicked() {
+                    ev_export.send(ExportCapture { csv: true });
+                }
+            });
+            if let Some(message) = &buffer.status {
+                ui.label(message);
+            }
         });
+}
+
+/// Offers the snapshot from the last crash dump, once.
+fn crash_ui(
+    mut contexts: EguiContexts,
+    mut report: ResMut<CrashReport>,
+    mut ev_load: EventWriter<LoadSnapshot>,
+) {
+    let (Some(dir), Some(snapshot)) = (report.dir.clone(), report.snapshot_path()) else {
+        return;
+    };
+    egui::Window::new("Previous Crash")
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("The last session crashed:");
+            ui.label(&report.summary);
+            ui.label(format!("Dump: {}", dir.display()));
+            ui.horizontal(|ui| {
+                if ui.button("Load Crash Snapshot").clicked() {
+                    ev_load.send(LoadSnapshot { path: snapshot });
+                    report.dismiss();
+                }
+                if ui.button("Dismiss").clicked() {
+                    report.dismiss();
+                }
+            });
+        });
+}
+
+/// Replay files on disk, rescanned when a recording is saved or on request.
+#[derive(Default)]
+struct ReplayBrowser {
+    files: Vec<(PathBuf, Result<ReplayHeader, String>)>,
+    scanned: bool,
+    last_status: Option<String>,
+}
+
+fn describe_age(secs: u64) -> String {
+    match secs {
+        0..=59 => "just now".into(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86_400),
     }
 }
 
+/// Recording controls and a list of saved replays with their metadata.
+fn replay_ui(
+    mut contexts: EguiContexts,
+    replay: Res<Replay>,
+    mut ev_replay: EventWriter<ReplayCommand>,
+    mut ev_ghost: EventWriter<GhostCommand>,
+    mut browser: Local<ReplayBrowser>,
+) {
+    if !browser.scanned || browser.last_status != replay.status {
+        browser.files = replay::list_replays();
+        browser.scanned = true;
+        browser.last_status = replay.status.clone();
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    egui::Window::new("Replays")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(ticks) = replay.recorded_ticks() {
+                ui.label(format!("Recording: tick {ticks}"));
+                if ui.button("Stop and Save").clicked() {
+                    ev_replay.send(ReplayCommand::Stop);
+                }
+            } else if let Some(pb) = replay.playback() {
+                let name = pb.path.file_name().unwrap_or_default().to_string_lossy();
+                ui.label(format!(
+                    "Playing {name}: tick {} / {}",
+                    pb.tick,
+                    pb.header().ticks
+                ));
+                if ui.button("Stop Playback").clicked() {
+                    ev_replay.send(ReplayCommand::Stop);
+                }
+            } else if ui
+                .button("Record")
+                .on_hover_text("Resets the scenario and logs every input from there")
+                .clicked()
+            {
+                ev_replay.send(ReplayCommand::Record);
+            }
+            if let Some(status) = &replay.status {
+                ui.label(status);
+            }
+
+            ui.separator();
+            if ui.button("Refresh").clicked() {
+                browser.scanned = false;
+            }
+            if browser.files.is_empty() {
+                ui.label(format!("No replays in {}/", replay::REPLAY_DIR));
+            }
+            let can_play = replay.recorded_ticks().is_none();
+            let idle = matches!(replay.mode, ReplayMode::Idle);
+            for (path, header) in &browser.files {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                ui.horizontal(|ui| {
+                    match header {
+                        Ok(h) => {
+                            if ui
+                                .add_enabled(can_play, egui::Button::new("Play"))
+                                .clicked()
+                            {
+                                ev_replay.send(ReplayCommand::Play(path.clone()));
+                            }
+                            if ui
+                                .add_enabled(can_play, egui::Button::new("View"))
+                                .on_hover_text("Open in the replay viewer")
+                                .clicked()
+                            {
+                                ev_replay.send(ReplayCommand::View(path.clone()));
+                            }
+                            if ui
+                                .add_enabled(
+                                    idle && h.track_samples > 0,
+                                    egui::Button::new("Ghost"),
+                                )
+                                .on_hover_text("Restart the run and race its recorded player")
+                                .clicked()
+                            {
+                                ev_ghost.send(GhostCommand::Load(path.clone()));
+                            }
+                            ui.label(format!(
+                                "{name}: {:?}, seed {}, {} ticks ({:.1}s), {} inputs, {}",
+                                h.scenario,
+                                h.seed,
+                                h.ticks,
+                                h.duration,
+                                h.inputs,
+                                describe_age(now.saturating_sub(h.recorded_at))
+                            ));
+                        }
+                        Err(e) => {
+                            ui.label(format!("{name}: unreadable ({e})"));
+                        }
+                    };
+                });
+            }
+        });
+}
+
+/// Live minus ghost, green when ahead.
+fn delta_label(ui: &mut egui::Ui, what: &str, delta: f32) {
+    let color = if delta >= 0.0 {
+        egui::Color32::from_rgb(120, 220, 120)
+    } else {
+        egui::Color32::from_rgb(255, 110, 110)
+    };
+    ui.colored_label(color, format!("{what}: {delta:+.1}"));
+}
+
+/// How the player stands against the ghost at the same sim time.
+fn ghost_ui(
+    mut contexts: EguiContexts,
+    ghost: Res<Ghost>,
+    clock: Res<SimClock>,
+    player_q: Query<(&Body, &Player)>,
+    mut ev_ghost: EventWriter<GhostCommand>,
+) {
+    if ghost.run.is_none() && ghost.status.is_none() {
+        return;
+    }
+    egui::Window::new("Ghost").show(contexts.ctx_mut(), |ui| {
+        if let Some(run) = &ghost.run {
+            let name = run.path.file_name().unwrap_or_default().to_string_lossy();
+            ui.label(format!(
+                "Racing {name}: {:.1} / {:.1} s",
+                clock.elapsed,
+                run.duration()
+            ));
+            let ghost_now = run.at(clock.elapsed);
+            match (ghost_now.or(run.finish().copied()), player_q.get_single()) {
+                (Some(sample), Ok((body, player))) => {
+                    if ghost_now.is_none() {
+                        ui.label("Ghost finished; comparing with its final state");
+                    }
+                    ui.label(format!(
+                        "Ghost: {:?}, mass {:.1}, score {:.0}",
+                        sample.class, sample.mass, sample.score
+                    ));
+                    delta_label(ui, "Mass", body.mass - sample.mass);
+                    delta_label(ui, "Score", player.score - sample.score);
+                }
+                (_, Err(_)) => {
+                    ui.label("No player");
+                }
+                (None, _) => {}
+            }
+        }
+        if let Some(status) = &ghost.status {
+            ui.label(status);
+        }
+        if ui.button("Clear Ghost").clicked() {
+            ev_ghost.send(GhostCommand::Clear);
+        }
+    });
+}
+
+fn marker_color(kind: MarkerKind) -> egui::Color32 {
+    match kind {
+        MarkerKind::Merge => egui::Color32::from_rgb(255, 170, 60),
+        MarkerKind::ClassChange => egui::Color32::from_rgb(120, 200, 255),
+        MarkerKind::Death => egui::Color32::from_rgb(255, 80, 80),
+    }
+}
+
+/// Transport, timeline and markers for the replay viewer.
+fn viewer_ui(
+    mut contexts: EguiContexts,
+    replay: Res<Replay>,
+    mut viewer: ResMut<Viewer>,
+    state_hash: Res<StateHash>,
+    mut ev_replay: EventWriter<ReplayCommand>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut dragged: Local<Option<u64>>,
+) {
+    egui::TopBottomPanel::bottom("replay_viewer").show(contexts.ctx_mut(), |ui| {
+        let exit = |ui: &mut egui::Ui,
+                    ev_replay: &mut EventWriter<ReplayCommand>,
+                    next_state: &mut NextState<AppState>| {
+            if ui.button("Exit Viewer").clicked() {
+                ev_replay.send(ReplayCommand::Stop);
+                next_state.set(AppState::Playing);
+            }
+        };
+        let Some(pb) = replay.playback() else {
+            ui.horizontal(|ui| {
+                ui.label(replay.status.as_deref().unwrap_or("No replay loaded"));
+                exit(ui, &mut ev_replay, &mut next_state);
+            });
+            return;
+        };
+        let (tick, end) = (pb.tick, pb.header().ticks);
+        let name = pb.path.file_name().unwrap_or_default().to_string_lossy();
+
+        ui.horizontal(|ui| {
+            if ui.button("|<").on_hover_text("Back to the start").clicked() {
+                viewer.seek = Some(0);
+            }
+            if ui
+                .button("<")
+                .on_hover_text("Step back one tick (,)")
+                .clicked()
+            {
+                viewer.paused = true;
+                viewer.seek = Some(tick.saturating_sub(1));
+            }
+            let play = if viewer.paused { "Play" } else { "Pause" };
+            if ui.button(play).on_hover_text("Space").clicked() {
+                if viewer.paused && tick == end {
+                    viewer.seek = Some(0);
+                }
+                viewer.paused = !viewer.paused;
+            }
+            if ui
+                .button(">")
+                .on_hover_text("Step forward one tick (.)")
+                .clicked()
+            {
+                viewer.paused = true;
+                viewer.steps += 1;
+            }
+            egui::ComboBox::from_id_salt("viewer_speed")
+                .selected_text(format!("{}x", viewer.speed))
+                .show_ui(ui, |ui| {
+                    for speed in viewer::SPEEDS {
+                        ui.selectable_value(&mut viewer.speed, speed, format!("{speed}x"));
+                    }
+                });
+            ui.label(format!("{name}: tick {tick} / {end}"));
+            if viewer.seek.is_some() {
+                ui.label("Seeking...");
+            } else if tick == end {
+                let expected = pb.header().final_hash;
+                ui.label(if state_hash.value == expected {
+                    "End: state matches the recording"
+                } else {
+                    "End: state differs from the recording"
+                });
+            }
+            exit(ui, &mut ev_replay, &mut next_state);
+        });
+
+        // The timeline, with a tick for every marker under the handle.
+        let mut shown = dragged.unwrap_or(viewer.seek.unwrap_or(tick));
+        ui.spacing_mut().slider_width = ui.available_width();
+        let response = ui.add(
+            egui::Slider::new(&mut shown, 0..=end.max(1))
+                .show_value(false)
+                .trailing_fill(true),
+        );
+        if response.dragged() {
+            *dragged = Some(shown);
+        } else if response.changed() || dragged.is_some() {
+            viewer.seek = Some(shown);
+            *dragged = None;
+        }
+        let rect = response.rect;
+        let painter = ui.painter();
+        for (marker_tick, kind, _) in viewer.markers() {
+            let x = rect.left() + rect.width() * marker_tick as f32 / end.max(1) as f32;
+            painter.vline(
+                x,
+                rect.y_range(),
+                egui::Stroke::new(2.0, marker_color(kind)),
+            );
+        }
+
+        let mut jump = None;
+        egui::CollapsingHeader::new(format!("Markers ({})", viewer.marker_count())).show(
+            ui,
+            |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for (marker_tick, kind, label) in viewer.markers() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button(format!("{marker_tick}")).clicked() {
+                                    jump = Some(marker_tick);
+                                }
+                                ui.colored_label(marker_color(kind), label);
+                            });
+                        }
+                    });
+            },
+        );
+        if let Some(target) = jump {
+            viewer.paused = true;
+            viewer.seek = Some(target);
+        }
+    });
+}
+
 fn game_over_ui(
     mut contexts: EguiContexts,
     mut ev_reset: EventWriter<ResetEvent>,
+    mut ev_load: EventWriter<LoadSnapshot>,
     mut next_state: ResMut<NextState<AppState>>,
+    status: Res<SnapshotStatus>,
+    rewind: Res<Rewind>,
 ) {
     egui::Window::new("Game Over").show(contexts.ctx_mut(), |ui| {
         ui.label("You Died!");
+        if rewind.enabled && rewind.keyframe_count() > 0 {
+            ui.label("Hold Z to rewind");
+        }
         if ui.button("Retry").clicked() {
-            ev_reset.send(ResetEvent::default());
+            ev_reset.send(ResetEvent);
             next_state.set(AppState::Playing);
         }
+        let quicksave = quicksave_path(SnapshotFormat::Ron);
+        if quicksave.exists() && ui.button("Load Quicksave").clicked() {
+            ev_load.send(LoadSnapshot { path: quicksave });
+        }
+        if let Some(message) = &status.message {
+            ui.label(message);
+        }
     });
 }
@@ -0,0 +1,195 @@
+//! Black-hole accretion: tidal disruption into a disk, viscous inspiral, and
+//! growth as material crosses the horizon.
+//!
+//! A body that strays inside a black hole's capture radius
+//! (`capture_factor` × its horizon, the sprite radius) is stretched radially
+//! into a string of [`DiskParticle`] fragments. Disk particles feel a viscous
+//! torque that bleeds off their angular momentum about the host and damps
+//! their radial motion, so they settle into a swirling disk and spiral in.
+//! Whatever falls inside the horizon is added to the hole's mass, with its
+//! momentum, and counted in [`AccretionStats`].
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::sph::GasParticle;
+use super::{spawn_body, Body, Class, Player, SimSettings, SimStats};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccretionSettings {
+    pub enabled: bool,
+    /// Capture radius in units of the horizon radius.
+    pub capture_factor: f32,
+    /// Pieces a disrupted body is torn into (fewer if they would be lighter than one unit).
+    pub fragments: usize,
+    /// Length of the tidal stream in units of the victim's radius.
+    pub stretch: f32,
+    /// Fraction of tangential velocity (relative to the host) lost per second.
+    pub viscosity: f32,
+    /// Fraction of radial velocity lost per second.
+    pub radial_damping: f32,
+}
+
+/// Off by default; the BH Arena preset turns it on.
+impl Default for AccretionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capture_factor: 5.0,
+            fragments: 12,
+            stretch: 8.0,
+            viscosity: 0.15,
+            radial_damping: 0.5,
+        }
+    }
+}
+
+/// A piece of a disrupted body orbiting inside `host`'s accretion disk.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DiskParticle {
+    pub host: Entity,
+}
+
+/// Mass swallowed by black holes.
+#[derive(Resource, Default, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AccretionStats {
+    /// Mass per sim second crossing any horizon, smoothed over about a second.
+    pub rate: f32,
+    pub total: f32,
+    pub disruptions: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Hole {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    horizon: f32,
+}
+
+pub(super) fn update_accretion_disks(
+    mut commands: Commands,
+    settings: Res<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    mut accretion: ResMut<AccretionStats>,
+    mut q: Query<(
+        Entity,
+        &mut Body,
+        &Transform,
+        Option<&DiskParticle>,
+        Has<Player>,
+        Has<GasParticle>,
+    )>,
+) {
+    let spec = settings.accretion;
+    if !settings.running || !spec.enabled {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    let holes: Vec<Hole> = q
+        .iter()
+        .filter(|(_, b, ..)| b.class == Class::BlackHole)
+        .map(|(e, b, t, ..)| Hole {
+            entity: e,
+            pos: t.translation.truncate(),
+            vel: b.vel,
+            horizon: Class::radius_for_mass(b.mass),
+        })
+        .collect();
+
+    // Mass and momentum crossing each horizon this step.
+    let mut swallowed: HashMap<Entity, (f32, Vec2)> = HashMap::new();
+    for (e, mut b, t, disk, player, gas) in &mut q {
+        let pos = t.translation.truncate();
+        if let Some(disk) = disk {
+            let Some(host) = holes.iter().find(|h| h.entity == disk.host) else {
+                // The host is gone; what is left drifts off as ordinary debris.
+                commands.entity(e).remove::<DiskParticle>();
+                continue;
+            };
+            let rel = pos - host.pos;
+            let dist = rel.length();
+            if dist <= host.horizon {
+                let fed = swallowed.entry(host.entity).or_default();
+                fed.0 += b.mass;
+                fed.1 += b.mass * b.vel;
+                commands.entity(e).despawn_recursive();
+                stats.0 = stats.0.saturating_sub(1);
+                continue;
+            }
+            let n = rel / dist;
+            let v_rel = b.vel - host.vel;
+            let v_r = v_rel.dot(n);
+            let v_t = v_rel - v_r * n;
+            // Never remove more than the current relative velocity in one step.
+            let k_t = spec.viscosity.min(1.0 / dt.max(1e-6));
+            let k_r = spec.radial_damping.min(1.0 / dt.max(1e-6));
+            let drag = -k_t * v_t - k_r * v_r * n;
+            b.acc += drag;
+            continue;
+        }
+        if b.class == Class::BlackHole || player || gas {
+            continue;
+        }
+        let Some(host) = holes
+            .iter()
+            .filter(|h| (pos - h.pos).length_squared() < (spec.capture_factor * h.horizon).powi(2))
+            .min_by(|a, b| {
+                (pos - a.pos)
+                    .length_squared()
+                    .total_cmp(&(pos - b.pos).length_squared())
+            })
+        else {
+            continue;
+        };
+
+        commands.entity(e).despawn_recursive();
+        stats.0 = stats.0.saturating_sub(1);
+        let rel = pos - host.pos;
+        if rel.length() <= host.horizon {
+            let fed = swallowed.entry(host.entity).or_default();
+            fed.0 += b.mass;
+            fed.1 += b.mass * b.vel;
+            continue;
+        }
+
+        // Spaghettify: a radial string of fragments sharing the victim's velocity.
+        accretion.disruptions += 1;
+        let count = spec.fragments.clamp(1, b.mass.max(1.0) as usize);
+        let piece = b.mass / count as f32;
+        let length = spec.stretch * Class::radius_for_mass(b.mass);
+        let n = rel.normalize_or_zero();
+        for k in 0..count {
+            let s = if count > 1 {
+                k as f32 / (count - 1) as f32 - 0.5
+            } else {
+                0.0
+            };
+            let p = pos + n * s * length;
+            let fragment = spawn_body(&mut commands, &settings, p, b.vel, piece);
+            commands
+                .entity(fragment)
+                .insert(DiskParticle { host: host.entity });
+        }
+        stats.0 += count;
+    }
+
+    let mut swallowed: Vec<(Entity, (f32, Vec2))> = swallowed.into_iter().collect();
+    swallowed.sort_by_key(|(hole, _)| *hole);
+    let mut gained = 0.0;
+    for (hole, (mass, momentum)) in swallowed {
+        if let Ok((_, mut b, ..)) = q.get_mut(hole) {
+            b.vel = (b.vel * b.mass + momentum) / (b.mass + mass);
+            b.mass += mass;
+            b.class = Class::from_mass(b.mass);
+            gained += mass;
+        }
+    }
+    accretion.total += gained;
+    if dt > 0.0 {
+        let blend = 1.0 - (-dt).exp();
+        accretion.rate += (gained / dt - accretion.rate) * blend;
+    }
+}
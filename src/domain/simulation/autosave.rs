@@ -0,0 +1,174 @@
+//! Autosaves and resuming the last session.
+//!
+//! While playing, a snapshot is taken every `interval` sim seconds and when
+//! the app exits, into `saves/autosave-<slot>.ron`, going round `slots`
+//! slots. Only the capture happens in the frame; encoding and writing run on
+//! a background thread (Bevy's task pools are built single-threaded here, so
+//! they would run it in the frame anyway), into a temporary file that replaces
+//! the slot once complete, so a crash mid-write never leaves a broken autosave. The save on
+//! exit is written straight away, as nothing would be left to finish it.
+//!
+//! On startup the newest autosave becomes [`Autosave::resume`], for the HUD to
+//! offer as "Continue". A snapshot holds the scenario, seed, settings and the
+//! player, so loading it picks the session up where it stopped.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use super::snapshot::{self, Snapshot, SnapshotFormat};
+use super::{AppState, SimClock};
+
+const SAVE_DIR: &str = "saves";
+
+const PREFIX: &str = "autosave-";
+
+/// Tick and path written, or what went wrong.
+type SlotResult = Result<(u64, PathBuf), String>;
+
+pub fn slot_path(slot: u32) -> PathBuf {
+    Path::new(SAVE_DIR).join(format!("{PREFIX}{slot}.ron"))
+}
+
+/// Autosave slots on disk as `(slot, path, modified)`.
+fn existing_slots() -> Vec<(u32, PathBuf, SystemTime)> {
+    let Ok(dir) = std::fs::read_dir(SAVE_DIR) else {
+        return Vec::new();
+    };
+    dir.filter_map(|entry| {
+        let path = entry.ok()?.path();
+        let slot = path
+            .file_name()?
+            .to_str()?
+            .strip_prefix(PREFIX)?
+            .strip_suffix(".ron")?
+            .parse()
+            .ok()?;
+        let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+        Some((slot, path, modified))
+    })
+    .collect()
+}
+
+#[derive(Resource)]
+pub struct Autosave {
+    pub enabled: bool,
+    /// Sim seconds between autosaves.
+    pub interval: f32,
+    pub slots: u32,
+    next_slot: u32,
+    /// Sim time of the last autosave.
+    last: f32,
+    writing: Option<JoinHandle<SlotResult>>,
+    /// The previous session's newest autosave, until it is resumed or
+    /// declined. Its slot is not overwritten meanwhile.
+    pub resume: Option<PathBuf>,
+    /// Outcome of the last autosave, for the HUD.
+    pub status: Option<String>,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 30.0,
+            slots: 3,
+            next_slot: 0,
+            last: 0.0,
+            writing: None,
+            resume: None,
+            status: None,
+        }
+    }
+}
+
+impl Autosave {
+    /// The slot to write next, skipping the one offered for resuming.
+    fn take_slot(&mut self) -> PathBuf {
+        let slots = self.slots.max(1);
+        let mut slot = self.next_slot % slots;
+        if slots > 1 && self.resume.as_ref() == Some(&slot_path(slot)) {
+            slot = (slot + 1) % slots;
+        }
+        self.next_slot = (slot + 1) % slots;
+        slot_path(slot)
+    }
+
+    /// Records the result of a finished write.
+    fn finish(&mut self, result: std::thread::Result<SlotResult>) {
+        self.status = Some(
+            match result.unwrap_or_else(|_| Err("writer panicked".into())) {
+                Ok((tick, path)) => format!("Autosaved tick {tick} to {}", path.display()),
+                Err(e) => format!("Autosave failed: {e}"),
+            },
+        );
+    }
+}
+
+/// Encodes and writes `snapshot` through a temporary file.
+fn write_slot(path: PathBuf, snapshot: Snapshot) -> SlotResult {
+    let bytes = snapshot::encode(&snapshot, SnapshotFormat::Ron).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(SAVE_DIR).map_err(|e| e.to_string())?;
+    let partial = path.with_extension("ron.partial");
+    std::fs::write(&partial, bytes).map_err(|e| e.to_string())?;
+    std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+    Ok((snapshot.clock.tick, path))
+}
+
+/// Offers the newest autosave and continues the rotation after it.
+pub(super) fn find_resume(mut autosave: ResMut<Autosave>) {
+    if let Some((slot, path, _)) = existing_slots().into_iter().max_by_key(|s| s.2) {
+        autosave.next_slot = (slot + 1) % autosave.slots.max(1);
+        autosave.resume = Some(path);
+    }
+}
+
+/// Starts an autosave every `interval` sim seconds and collects finished ones.
+pub(super) fn autosave(world: &mut World) {
+    let elapsed = world.resource::<SimClock>().elapsed;
+    let mut autosave = world.resource_mut::<Autosave>();
+    if autosave
+        .writing
+        .as_ref()
+        .is_some_and(JoinHandle::is_finished)
+    {
+        let result = autosave.writing.take().expect("checked above").join();
+        autosave.finish(result);
+    }
+    // A reset or a loaded snapshot restarts the interval.
+    if elapsed < autosave.last {
+        autosave.last = elapsed;
+    }
+    if !autosave.enabled
+        || autosave.writing.is_some()
+        || elapsed - autosave.last < autosave.interval
+    {
+        return;
+    }
+    autosave.last = elapsed;
+    let path = autosave.take_slot();
+    let snapshot = snapshot::capture(world);
+    let writer = std::thread::spawn(move || write_slot(path, snapshot));
+    world.resource_mut::<Autosave>().writing = Some(writer);
+}
+
+/// Saves once more as the app exits, waiting for any write still running.
+pub(super) fn save_on_exit(world: &mut World) {
+    if world.resource::<Events<AppExit>>().is_empty()
+        || !world.resource::<Autosave>().enabled
+        || *world.resource::<State<AppState>>().get() != AppState::Playing
+    {
+        return;
+    }
+    let mut autosave = world.resource_mut::<Autosave>();
+    if let Some(writer) = autosave.writing.take() {
+        let result = writer.join();
+        autosave.finish(result);
+    }
+    let path = autosave.take_slot();
+    let snapshot = snapshot::capture(world);
+    let result = write_slot(path, snapshot);
+    world.resource_mut::<Autosave>().finish(Ok(result));
+}
@@ -0,0 +1,225 @@
+//! Rolling capture of recent body states.
+//!
+//! Every `every` ticks the [`CaptureBuffer`] keeps a copy of every body
+//! (stable id, `Entity`, class, position, velocity, mass), and forgets frames
+//! older than `seconds` of sim time. Nothing has to be started beforehand:
+//! after something surprising happens, [`ExportCapture`] writes what the
+//! buffer holds to `captures/` as JSON, optionally with a flat CSV beside it
+//! (one row per body per frame) for spreadsheets and plotting scripts.
+
+use bevy::prelude::*;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, BufWriter, Write as _};
+use std::path::{Path, PathBuf};
+
+use super::replay::unix_now;
+use super::{Body, BodyId, Class, Player, Scenario, SimClock, SimSettings};
+
+pub const CAPTURE_DIR: &str = "captures";
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CapturedBody {
+    pub id: u64,
+    /// `Entity::to_bits`, to match against logs and the inspector.
+    pub entity: u64,
+    pub class: Class,
+    pub player: bool,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub mass: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CaptureFrame {
+    pub tick: u64,
+    /// Sim seconds since the run started.
+    pub time: f32,
+    /// Sorted by id.
+    pub bodies: Vec<CapturedBody>,
+}
+
+#[derive(Resource)]
+pub struct CaptureBuffer {
+    pub enabled: bool,
+    /// Sim seconds kept.
+    pub seconds: f32,
+    /// Capture every this many ticks.
+    pub every: u32,
+    frames: VecDeque<CaptureFrame>,
+    /// Outcome of the last export, for the HUD.
+    pub status: Option<String>,
+}
+
+impl Default for CaptureBuffer {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            seconds: 10.0,
+            every: 5,
+            frames: VecDeque::new(),
+            status: None,
+        }
+    }
+}
+
+impl CaptureBuffer {
+    pub fn frames(&self) -> &VecDeque<CaptureFrame> {
+        &self.frames
+    }
+
+    /// Sim seconds between the oldest and newest frame.
+    pub fn span(&self) -> f32 {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    fn push(&mut self, frame: CaptureFrame) {
+        // Time running backwards means a reset or a loaded snapshot: the old
+        // frames belong to another run.
+        if self
+            .frames
+            .back()
+            .is_some_and(|last| frame.time < last.time)
+        {
+            self.frames.clear();
+        }
+        let oldest = frame.time - self.seconds;
+        self.frames.push_back(frame);
+        while self.frames.front().is_some_and(|f| f.time < oldest) {
+            self.frames.pop_front();
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CaptureExport<'a> {
+    scenario: Scenario,
+    seed: u64,
+    dt: f32,
+    every: u32,
+    frames: &'a VecDeque<CaptureFrame>,
+}
+
+fn write_json(path: &Path, export: &CaptureExport) -> io::Result<()> {
+    let mut out = BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer(&mut out, export)?;
+    out.flush()
+}
+
+fn write_csv(path: &Path, frames: &VecDeque<CaptureFrame>) -> io::Result<()> {
+    let mut out = BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, "tick,time,id,entity,class,player,x,y,vx,vy,mass")?;
+    let mut row = String::new();
+    for frame in frames {
+        for b in &frame.bodies {
+            row.clear();
+            let _ = write!(
+                row,
+                "{},{},{},{},{:?},{},{},{},{},{},{}",
+                frame.tick,
+                frame.time,
+                b.id,
+                b.entity,
+                b.class,
+                b.player,
+                b.pos.x,
+                b.pos.y,
+                b.vel.x,
+                b.vel.y,
+                b.mass
+            );
+            writeln!(out, "{row}")?;
+        }
+    }
+    out.flush()
+}
+
+/// Writes the buffer to `captures/capture-<unix time>.json` (and `.csv` when
+/// asked), returning the JSON path.
+fn export(buffer: &CaptureBuffer, settings: &SimSettings, csv: bool) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(CAPTURE_DIR)?;
+    let path = Path::new(CAPTURE_DIR).join(format!("capture-{}.json", unix_now()));
+    write_json(
+        &path,
+        &CaptureExport {
+            scenario: settings.scenario,
+            seed: settings.seed,
+            dt: settings.dt,
+            every: buffer.every,
+            frames: &buffer.frames,
+        },
+    )?;
+    if csv {
+        write_csv(&path.with_extension("csv"), &buffer.frames)?;
+    }
+    Ok(path)
+}
+
+/// Dump the capture buffer to disk.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ExportCapture {
+    /// Also write a CSV next to the JSON.
+    pub csv: bool,
+}
+
+/// Adds this tick's bodies to the buffer every `every` ticks while running.
+/// Runs after ids are assigned, so every body has one.
+pub(super) fn record_frame(
+    settings: Res<SimSettings>,
+    clock: Res<SimClock>,
+    mut buffer: ResMut<CaptureBuffer>,
+    q: Query<(Entity, &BodyId, &Body, &Transform, Has<Player>)>,
+) {
+    if !buffer.enabled
+        || !settings.running
+        || !clock.tick.is_multiple_of(u64::from(buffer.every.max(1)))
+    {
+        return;
+    }
+    let mut bodies: Vec<CapturedBody> = q
+        .iter()
+        .map(|(e, id, body, t, player)| CapturedBody {
+            id: id.0,
+            entity: e.to_bits(),
+            class: body.class,
+            player,
+            pos: t.translation.truncate(),
+            vel: body.vel,
+            mass: body.mass,
+        })
+        .collect();
+    bodies.sort_by_key(|b| b.id);
+    buffer.push(CaptureFrame {
+        tick: clock.tick,
+        time: clock.elapsed,
+        bodies,
+    });
+}
+
+pub(super) fn export_capture(
+    mut ev_export: EventReader<ExportCapture>,
+    mut buffer: ResMut<CaptureBuffer>,
+    settings: Res<SimSettings>,
+) {
+    let Some(request) = ev_export.read().last().copied() else {
+        return;
+    };
+    let message = if buffer.frames.is_empty() {
+        "Capture buffer is empty".to_string()
+    } else {
+        match export(&buffer, &settings, request.csv) {
+            Ok(path) => format!(
+                "Exported {} frames ({:.1} s) to {}",
+                buffer.frames.len(),
+                buffer.span(),
+                path.display()
+            ),
+            Err(e) => format!("Capture export failed: {e}"),
+        }
+    };
+    buffer.status = Some(message);
+}
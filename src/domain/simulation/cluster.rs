@@ -0,0 +1,345 @@
+//! Equilibrium star-cluster generators.
+//!
+//! Radii are drawn from a Plummer or King density profile, speeds from the
+//! matching distribution function, and masses from an initial mass function.
+//! The result is then rescaled so the cluster starts in virial equilibrium
+//! (2T = |W|) under the sim's own `g` and softening.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+
+use super::initial_conditions::BodyInit;
+
+/// Density profile to sample positions and velocities from.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ClusterModel {
+    Plummer,
+    /// King (1966) lowered isothermal model with central potential `w0` (typically 3–9).
+    King {
+        w0: f32,
+    },
+}
+
+/// Initial mass function used to draw individual masses.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Imf {
+    /// Every body gets the same mass.
+    Equal,
+    /// Single power law, dN/dm ∝ m^-2.35.
+    Salpeter,
+    /// Broken power law with slopes 0.3 / 1.3 / 2.3 at 0.08 and 0.5 M☉.
+    #[default]
+    Kroupa,
+}
+
+/// Parameters for [`generate`].
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterSpec {
+    pub model: ClusterModel,
+    pub imf: Imf,
+    /// IMF sampling range in solar masses; masses are rescaled to `total_mass` afterwards.
+    pub imf_range: (f32, f32),
+    pub count: usize,
+    pub total_mass: f32,
+    /// Plummer radius, or King core radius.
+    pub scale_radius: f32,
+    /// Target virial ratio T/|W|; 0.5 is equilibrium, lower values collapse, higher expand.
+    pub virial_ratio: f32,
+    /// Samples the cluster from its own generator, so it comes out the same
+    /// whatever the run's seed; `None` draws from the caller's stream.
+    pub seed: Option<u64>,
+}
+
+impl Default for ClusterSpec {
+    fn default() -> Self {
+        Self {
+            model: ClusterModel::Plummer,
+            imf: Imf::Kroupa,
+            imf_range: (0.1, 20.0),
+            count: 120,
+            total_mass: 2.4e6,
+            scale_radius: 600.0,
+            virial_ratio: 0.5,
+            seed: None,
+        }
+    }
+}
+
+/// Samples a cluster centred on `centre` and rescales its velocities to `spec.virial_ratio`.
+///
+/// `softening` gives the softening length the solver will use at each of the
+/// given positions, which with adaptive softening depends on the crowding.
+pub fn generate<R: Rng + ?Sized>(
+    g: f32,
+    softening: impl FnOnce(&[Vec2]) -> Vec<f32>,
+    spec: &ClusterSpec,
+    centre: Vec2,
+    rng: &mut R,
+) -> Vec<BodyInit> {
+    match spec.seed {
+        Some(seed) => sample_cluster(
+            g,
+            softening,
+            spec,
+            centre,
+            &mut ChaCha8Rng::seed_from_u64(seed),
+        ),
+        None => sample_cluster(g, softening, spec, centre, rng),
+    }
+}
+
+fn sample_cluster<R: Rng + ?Sized>(
+    g: f32,
+    softening: impl FnOnce(&[Vec2]) -> Vec<f32>,
+    spec: &ClusterSpec,
+    centre: Vec2,
+    rng: &mut R,
+) -> Vec<BodyInit> {
+    if spec.count == 0 {
+        return Vec::new();
+    }
+
+    // Masses
+    let mut masses: Vec<f32> = (0..spec.count)
+        .map(|_| sample_imf(spec.imf, spec.imf_range, rng))
+        .collect();
+    let sum: f32 = masses.iter().sum();
+    for m in &mut masses {
+        *m *= spec.total_mass / sum;
+    }
+
+    // Shape: dimensionless radius and speed, laid into the sim plane at random angles.
+    let king = match spec.model {
+        ClusterModel::King { w0 } => Some(KingProfile::solve(w0)),
+        ClusterModel::Plummer => None,
+    };
+    let mut bodies: Vec<BodyInit> = masses
+        .into_iter()
+        .map(|mass| {
+            let (r, v) = match &king {
+                Some(k) => k.sample(rng),
+                None => sample_plummer(rng),
+            };
+            let pos = Vec2::from_angle(rng.gen::<f32>() * TAU) * r * spec.scale_radius;
+            let vel = Vec2::from_angle(rng.gen::<f32>() * TAU) * v;
+            BodyInit { pos, vel, mass }
+        })
+        .collect();
+
+    // Move to the centre-of-mass frame.
+    let total: f32 = bodies.iter().map(|b| b.mass).sum();
+    let com = bodies.iter().map(|b| b.pos * b.mass).sum::<Vec2>() / total;
+    let com_vel = bodies.iter().map(|b| b.vel * b.mass).sum::<Vec2>() / total;
+    for b in &mut bodies {
+        b.pos += centre - com;
+        b.vel -= com_vel;
+    }
+
+    // Rescale speeds so T = Q·|W| with the solver's softened potential. The
+    // solver softens each pull by the receiving body's length, so a pair's
+    // potential uses the mean of the two.
+    let positions: Vec<Vec2> = bodies.iter().map(|b| b.pos).collect();
+    let soft2: Vec<f32> = softening(&positions).iter().map(|s| s * s).collect();
+    let mut potential = 0.0_f64;
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let d2 = (bodies[i].pos - bodies[j].pos).length_squared() + 0.5 * (soft2[i] + soft2[j]);
+            potential -= (g * bodies[i].mass * bodies[j].mass) as f64 / (d2 as f64).sqrt();
+        }
+    }
+    let kinetic: f64 = bodies
+        .iter()
+        .map(|b| 0.5 * b.mass as f64 * b.vel.length_squared() as f64)
+        .sum();
+    if kinetic > 0.0 {
+        let scale = ((spec.virial_ratio as f64 * potential.abs()) / kinetic).sqrt() as f32;
+        for b in &mut bodies {
+            b.vel *= scale;
+        }
+    }
+    bodies
+}
+
+/// Draws one mass (in solar units) from `imf` restricted to `range`.
+pub fn sample_imf<R: Rng + ?Sized>(imf: Imf, range: (f32, f32), rng: &mut R) -> f32 {
+    let (lo, hi) = (range.0.max(1e-3), range.1.max(range.0.max(1e-3)));
+    match imf {
+        Imf::Equal => 1.0,
+        Imf::Salpeter => sample_power_law(lo, hi, 2.35, rng.gen()),
+        Imf::Kroupa => {
+            // (segment start, segment end, slope)
+            const SEGMENTS: [(f32, f32, f32); 3] = [
+                (0.01, 0.08, 0.3),
+                (0.08, 0.5, 1.3),
+                (0.5, f32::INFINITY, 2.3),
+            ];
+            // Continuity coefficients so dN/dm is continuous at the breaks.
+            let mut coeff = [1.0_f32; 3];
+            for i in 1..SEGMENTS.len() {
+                let b = SEGMENTS[i].0;
+                coeff[i] = coeff[i - 1] * b.powf(SEGMENTS[i].2 - SEGMENTS[i - 1].2);
+            }
+            let mut weights = [0.0_f32; 3];
+            for (i, &(s0, s1, a)) in SEGMENTS.iter().enumerate() {
+                let (a0, a1) = (s0.max(lo), s1.min(hi));
+                if a1 > a0 {
+                    weights[i] = coeff[i] * power_law_integral(a0, a1, a);
+                }
+            }
+            let total: f32 = weights.iter().sum();
+            if total <= 0.0 {
+                return lo;
+            }
+            let mut pick = rng.gen::<f32>() * total;
+            for (i, &(s0, s1, a)) in SEGMENTS.iter().enumerate() {
+                if pick <= weights[i] || i == SEGMENTS.len() - 1 {
+                    return sample_power_law(s0.max(lo), s1.min(hi), a, rng.gen());
+                }
+                pick -= weights[i];
+            }
+            lo
+        }
+    }
+}
+
+fn power_law_integral(a: f32, b: f32, alpha: f32) -> f32 {
+    if (alpha - 1.0).abs() < 1e-4 {
+        (b / a).ln()
+    } else {
+        (b.powf(1.0 - alpha) - a.powf(1.0 - alpha)) / (1.0 - alpha)
+    }
+}
+
+/// Inverse-CDF sample of dN/dm ∝ m^-alpha on [a, b].
+fn sample_power_law(a: f32, b: f32, alpha: f32, u: f32) -> f32 {
+    if b <= a {
+        return a;
+    }
+    if (alpha - 1.0).abs() < 1e-4 {
+        return a * (b / a).powf(u);
+    }
+    let k = 1.0 - alpha;
+    (a.powf(k) + u * (b.powf(k) - a.powf(k))).powf(1.0 / k)
+}
+
+/// Aarseth, Hénon & Wielen (1974) sampling in units G = M = a = 1.
+fn sample_plummer<R: Rng + ?Sized>(rng: &mut R) -> (f32, f32) {
+    let r = loop {
+        let x: f32 = rng.gen_range(1e-4..1.0);
+        let r = 1.0 / (x.powf(-2.0 / 3.0) - 1.0).sqrt();
+        // Trim the extended halo; the outer few percent would just fly off screen.
+        if r < 10.0 {
+            break r;
+        }
+    };
+    let q = loop {
+        let q: f32 = rng.gen();
+        let y: f32 = rng.gen::<f32>() * 0.1;
+        if y < q * q * (1.0 - q * q).powf(3.5) {
+            break q;
+        }
+    };
+    let v = q * 2.0_f32.sqrt() * (1.0 + r * r).powf(-0.25);
+    (r, v)
+}
+
+/// Numerical King model: radius grid with dimensionless potential and cumulative mass.
+struct KingProfile {
+    r: Vec<f32>,
+    w: Vec<f32>,
+    mass: Vec<f32>,
+}
+
+impl KingProfile {
+    /// Integrates Poisson's equation outward from W(0) = w0 until W reaches zero (the tidal radius).
+    fn solve(w0: f32) -> Self {
+        let w0 = w0.clamp(0.5, 15.0);
+        let dr = 0.002_f32;
+        let (mut r, mut w, mut dw) = (dr, w0, 0.0_f32);
+        let mut out = Self {
+            r: vec![0.0],
+            w: vec![w0],
+            mass: vec![0.0],
+        };
+        let rho0 = king_density(w0);
+        // In core-radius units: W'' + (2/r) W' = -9 ρ(W)/ρ0.
+        let deriv = |r: f32, w: f32, dw: f32| -> (f32, f32) {
+            (dw, -9.0 * king_density(w) / rho0 - 2.0 * dw / r)
+        };
+        let mut m = 0.0_f32;
+        while w > 0.0 && r < 500.0 {
+            let (k1w, k1d) = deriv(r, w, dw);
+            let (k2w, k2d) = deriv(r + 0.5 * dr, w + 0.5 * dr * k1w, dw + 0.5 * dr * k1d);
+            let (k3w, k3d) = deriv(r + 0.5 * dr, w + 0.5 * dr * k2w, dw + 0.5 * dr * k2d);
+            let (k4w, k4d) = deriv(r + dr, w + dr * k3w, dw + dr * k3d);
+            w += dr / 6.0 * (k1w + 2.0 * k2w + 2.0 * k3w + k4w);
+            dw += dr / 6.0 * (k1d + 2.0 * k2d + 2.0 * k3d + k4d);
+            r += dr;
+            m += 4.0 * PI * r * r * king_density(w.max(0.0)) * dr;
+            out.r.push(r);
+            out.w.push(w.max(0.0));
+            out.mass.push(m);
+        }
+        out
+    }
+
+    /// Returns (radius in core radii, speed in units of σ).
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> (f32, f32) {
+        let total = *self.mass.last().unwrap_or(&0.0);
+        let target = rng.gen::<f32>() * total;
+        let i = self
+            .mass
+            .partition_point(|&m| m < target)
+            .clamp(1, self.r.len() - 1);
+        let (m0, m1) = (self.mass[i - 1], self.mass[i]);
+        let t = if m1 > m0 {
+            (target - m0) / (m1 - m0)
+        } else {
+            0.0
+        };
+        let r = self.r[i - 1] + t * (self.r[i] - self.r[i - 1]);
+        let w = self.w[i - 1] + t * (self.w[i] - self.w[i - 1]);
+
+        // Lowered Maxwellian: p(v) ∝ v² (e^(W − v²/2) − 1) for v < √(2W).
+        let vmax = (2.0 * w).sqrt();
+        if vmax <= 0.0 {
+            return (r, 0.0);
+        }
+        let pdf = |v: f32| v * v * ((w - 0.5 * v * v).exp() - 1.0);
+        let peak = (0..=32)
+            .map(|k| pdf(vmax * k as f32 / 32.0))
+            .fold(0.0_f32, f32::max)
+            * 1.1;
+        for _ in 0..256 {
+            let v = rng.gen::<f32>() * vmax;
+            if rng.gen::<f32>() * peak <= pdf(v) {
+                return (r, v);
+            }
+        }
+        (r, vmax * 0.5)
+    }
+}
+
+/// King (1966) density in units of ρ1: e^W erf(√W) − √(4W/π)(1 + 2W/3).
+fn king_density(w: f32) -> f32 {
+    if w <= 0.0 {
+        return 0.0;
+    }
+    let sw = w.sqrt();
+    (w.exp() * erf(sw) - (4.0 * w / PI).sqrt() * (1.0 + 2.0 * w / 3.0)).max(0.0)
+}
+
+/// Abramowitz & Stegun 7.1.26 (|error| < 1.5e-7).
+fn erf(x: f32) -> f32 {
+    let sign = x.signum();
+    let x = x.abs() as f64;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp()) as f32
+}
@@ -0,0 +1,262 @@
+//! Crash dumps.
+//!
+//! Every tick the [`CrashLog`] notes the state hash, any input or settings
+//! change and, every `snapshot_every` ticks, a full [`Snapshot`]. It keeps
+//! about [`HISTORY_TICKS`] ticks of this in memory, behind a lock the panic
+//! hook shares. When anything panics, the hook writes what it holds to
+//! `crashes/crash-<unix time>/`:
+//!
+//! - `panic.txt`: the message, where it was raised and the last tick
+//! - `backtrace.txt`
+//! - `snapshot.ron`: the last snapshot, loadable like any save
+//! - `inputs.ron`: inputs since well before that snapshot, as replay entries
+//!   keyed by sim tick
+//! - `settings.ron`: the settings as of the crash
+//! - `hashes.txt`: one `tick hash` line per recent tick
+//!
+//! Loading the snapshot and feeding the inputs after its tick back in should
+//! reach the crash again. On the next launch, [`CrashReport`] points at the
+//! newest dump nobody has looked at yet so the HUD can offer to load it.
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+
+use super::replay::{settings_change, unix_now, ReplayEntry, ReplayInput};
+use super::snapshot::{self, Snapshot, SnapshotFormat};
+use super::state_hash::StateHash;
+use super::{BurstRequest, PlayerInput, SimClock, SimSettings};
+
+pub const CRASH_DIR: &str = "crashes";
+
+/// Ticks of hashes and inputs kept for the dump.
+pub const HISTORY_TICKS: u64 = 600;
+
+/// Written into a dump once the player has been offered it.
+const SEEN_MARKER: &str = "seen";
+
+/// What the panic hook can reach.
+#[derive(Default)]
+struct CrashRecord {
+    tick: u64,
+    snapshot: Option<Snapshot>,
+    settings: Option<SimSettings>,
+    input: PlayerInput,
+    inputs: VecDeque<ReplayEntry>,
+    hashes: VecDeque<(u64, u64)>,
+}
+
+impl CrashRecord {
+    /// Forgets everything when the tick runs backwards (a reset or a loaded
+    /// snapshot) and anything older than [`HISTORY_TICKS`].
+    fn trim(&mut self, tick: u64) {
+        if tick < self.tick {
+            self.snapshot = None;
+            self.inputs.clear();
+            self.hashes.clear();
+        }
+        self.tick = tick;
+        let oldest = tick.saturating_sub(HISTORY_TICKS);
+        while self.hashes.front().is_some_and(|&(t, _)| t < oldest) {
+            self.hashes.pop_front();
+        }
+        while self.inputs.front().is_some_and(|e| e.tick < oldest) {
+            self.inputs.pop_front();
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct CrashLog {
+    /// Keep a snapshot for the dump every this many ticks.
+    pub snapshot_every: u32,
+    record: Arc<Mutex<CrashRecord>>,
+}
+
+impl Default for CrashLog {
+    fn default() -> Self {
+        Self {
+            snapshot_every: 120,
+            record: Arc::default(),
+        }
+    }
+}
+
+/// The newest crash dump not yet offered to the player.
+#[derive(Resource, Default)]
+pub struct CrashReport {
+    pub dir: Option<PathBuf>,
+    /// First line of the dump's `panic.txt`.
+    pub summary: String,
+}
+
+impl CrashReport {
+    pub fn snapshot_path(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join("snapshot.ron"))
+    }
+
+    /// Marks the dump as seen so later launches stop offering it.
+    pub fn dismiss(&mut self) {
+        if let Some(dir) = self.dir.take() {
+            let _ = std::fs::write(dir.join(SEEN_MARKER), "");
+        }
+    }
+}
+
+/// Only the first panic is dumped; others follow from it.
+static DUMPED: AtomicBool = AtomicBool::new(false);
+
+fn to_ron(value: &impl Serialize) -> std::io::Result<String> {
+    ron::ser::to_string_pretty(value, PrettyConfig::new().depth_limit(3))
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+fn write_dump(record: &Mutex<CrashRecord>, info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let dir = Path::new(CRASH_DIR).join(format!("crash-{}", unix_now()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("backtrace.txt"),
+        Backtrace::force_capture().to_string(),
+    )?;
+
+    // The lock is only ever held for a moment between ticks, so it is free
+    // unless the panic came from a thread holding it.
+    let record = match record.try_lock() {
+        Ok(record) => Some(record),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    };
+    let thread = std::thread::current();
+    let mut panic = format!(
+        "{}\nat {}\nthread {}\n",
+        info.payload_as_str()
+            .unwrap_or("(non-string panic payload)"),
+        info.location()
+            .map_or("an unknown location".to_string(), |l| l.to_string()),
+        thread.name().unwrap_or("<unnamed>"),
+    );
+    let Some(record) = record else {
+        panic.push_str("simulation state unavailable\n");
+        std::fs::write(dir.join("panic.txt"), panic)?;
+        return Ok(dir);
+    };
+    let _ = writeln!(panic, "last tick {}", record.tick);
+    std::fs::write(dir.join("panic.txt"), panic)?;
+
+    if let Some(snapshot) = &record.snapshot {
+        snapshot::write_file(&dir.join("snapshot.ron"), snapshot, SnapshotFormat::Ron)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    if let Some(settings) = &record.settings {
+        std::fs::write(dir.join("settings.ron"), to_ron(settings)?)?;
+    }
+    std::fs::write(dir.join("inputs.ron"), to_ron(&record.inputs)?)?;
+    let mut hashes = String::new();
+    for (tick, hash) in &record.hashes {
+        let _ = writeln!(hashes, "{tick} {hash:016x}");
+    }
+    std::fs::write(dir.join("hashes.txt"), hashes)?;
+    Ok(dir)
+}
+
+/// Chains a hook that writes the dump after the default panic message.
+pub(super) fn install_panic_hook(log: Res<CrashLog>) {
+    let record = Arc::clone(&log.record);
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if DUMPED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        match write_dump(&record, info) {
+            Ok(dir) => eprintln!("crash dump written to {}", dir.display()),
+            Err(e) => eprintln!("could not write crash dump: {e}"),
+        }
+    }));
+}
+
+/// Finds the newest dump with a snapshot that has not been offered yet.
+pub(super) fn find_crash_report(mut report: ResMut<CrashReport>) {
+    let Ok(entries) = std::fs::read_dir(CRASH_DIR) else {
+        return;
+    };
+    let newest = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|dir| dir.join("snapshot.ron").is_file() && !dir.join(SEEN_MARKER).exists())
+        .max();
+    if let Some(dir) = newest {
+        report.summary = std::fs::read_to_string(dir.join("panic.txt"))
+            .ok()
+            .and_then(|text| text.lines().next().map(str::to_owned))
+            .unwrap_or_default();
+        report.dir = Some(dir);
+    }
+}
+
+/// Notes this tick's hash and any input or settings change.
+pub(super) fn record_tick(
+    log: Res<CrashLog>,
+    clock: Res<SimClock>,
+    state_hash: Res<StateHash>,
+    input: Res<PlayerInput>,
+    settings: Res<SimSettings>,
+    mut ev_burst: EventReader<BurstRequest>,
+) {
+    let Ok(mut record) = log.record.lock() else {
+        return;
+    };
+    let tick = clock.tick;
+    record.trim(tick);
+    record.hashes.push_back((tick, state_hash.value));
+
+    let change = match &record.settings {
+        Some(old) => settings_change(old, &settings),
+        None => Some(ReplayInput::Settings(Box::new(settings.clone()))),
+    };
+    if let Some(change) = change {
+        record.inputs.push_back(ReplayEntry {
+            tick,
+            input: change,
+        });
+    }
+    if settings.is_changed() || record.settings.is_none() {
+        record.settings = Some(settings.clone());
+    }
+    if *input != record.input {
+        record.input = *input;
+        record.inputs.push_back(ReplayEntry {
+            tick,
+            input: ReplayInput::Thrust {
+                dir: input.thrust,
+                boost: input.boost,
+            },
+        });
+    }
+    for burst in ev_burst.read() {
+        record.inputs.push_back(ReplayEntry {
+            tick,
+            input: ReplayInput::Burst(burst.0),
+        });
+    }
+}
+
+/// Keeps a snapshot for the dump every `snapshot_every` ticks.
+pub(super) fn record_snapshot(world: &mut World) {
+    let log = world.resource::<CrashLog>();
+    let tick = world.resource::<SimClock>().tick;
+    if !tick.is_multiple_of(u64::from(log.snapshot_every.max(1))) {
+        return;
+    }
+    let record = Arc::clone(&log.record);
+    let snapshot = snapshot::capture(world);
+    if let Ok(mut record) = record.lock() {
+        record.snapshot = Some(snapshot);
+    };
+}
@@ -0,0 +1,161 @@
+//! Static (analytic) external potentials that push bodies around without being bodies.
+//!
+//! Fields live either in the [`ExternalFields`] resource (positions are world
+//! coordinates) or as an [`ExternalField`] component on any entity with a
+//! `Transform` (positions are relative to that entity). `apply_external_fields`
+//! sums them into `Body::acc` after the tree forces, before the second kick.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::galaxy::HaloModel;
+use super::{Body, SimClock, SimSettings};
+
+/// One analytic force source. All accelerations scale with `SimSettings::g`
+/// except the velocity-parametrised halos, which are defined by their circular speed.
+#[derive(Component, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ExternalField {
+    /// A mass pinned in place, Plummer-softened by `softening`.
+    PointMass {
+        pos: Vec2,
+        mass: f32,
+        softening: f32,
+    },
+    /// Constant acceleration everywhere (e.g. a "wind" or a falling frame).
+    Uniform { acc: Vec2 },
+    /// Φ = ½ v0² ln(rc² + x² + y²/q²); flat rotation curve `v0` outside the core.
+    LogarithmicHalo {
+        centre: Vec2,
+        v0: f32,
+        core_radius: f32,
+        /// Axis ratio q of the potential (1 = round).
+        flattening: f32,
+    },
+    /// A spherical dark-matter halo (see [`HaloModel`]).
+    Halo { centre: Vec2, model: HaloModel },
+    /// A bar rotating at `pattern_speed` rad/s, modelled as two softened masses at its tips.
+    RotatingBar {
+        centre: Vec2,
+        mass: f32,
+        half_length: f32,
+        pattern_speed: f32,
+        phase: f32,
+        softening: f32,
+    },
+    /// Linearised tide of a distant host of `host_mass` at `distance` along `angle`,
+    /// which sweeps around at `angular_speed` rad/s (0 for a fixed host).
+    Tidal {
+        centre: Vec2,
+        host_mass: f32,
+        distance: f32,
+        angle: f32,
+        angular_speed: f32,
+    },
+}
+
+impl ExternalField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExternalField::PointMass { .. } => "Point mass",
+            ExternalField::Uniform { .. } => "Uniform",
+            ExternalField::LogarithmicHalo { .. } => "Logarithmic halo",
+            ExternalField::Halo { .. } => "Halo",
+            ExternalField::RotatingBar { .. } => "Rotating bar",
+            ExternalField::Tidal { .. } => "Tidal",
+        }
+    }
+
+    /// Acceleration at `pos` (in the field's own frame) at sim time `time`.
+    pub fn acceleration(&self, g: f32, pos: Vec2, time: f32) -> Vec2 {
+        match *self {
+            ExternalField::PointMass {
+                pos: p,
+                mass,
+                softening,
+            } => softened_pull(g, mass, p - pos, softening),
+            ExternalField::Uniform { acc } => acc,
+            ExternalField::LogarithmicHalo {
+                centre,
+                v0,
+                core_radius,
+                flattening,
+            } => {
+                let d = pos - centre;
+                let q2 = (flattening * flattening).max(1e-4);
+                let denom = core_radius * core_radius + d.x * d.x + d.y * d.y / q2;
+                if denom <= 0.0 {
+                    return Vec2::ZERO;
+                }
+                -v0 * v0 * Vec2::new(d.x, d.y / q2) / denom
+            }
+            ExternalField::Halo { centre, model } => model.acceleration(g, pos - centre),
+            ExternalField::RotatingBar {
+                centre,
+                mass,
+                half_length,
+                pattern_speed,
+                phase,
+                softening,
+            } => {
+                let axis = Vec2::from_angle(phase + pattern_speed * time) * half_length;
+                softened_pull(g, 0.5 * mass, centre + axis - pos, softening)
+                    + softened_pull(g, 0.5 * mass, centre - axis - pos, softening)
+            }
+            ExternalField::Tidal {
+                centre,
+                host_mass,
+                distance,
+                angle,
+                angular_speed,
+            } => {
+                if distance <= 0.0 {
+                    return Vec2::ZERO;
+                }
+                let d = pos - centre;
+                let e = Vec2::from_angle(angle + angular_speed * time);
+                g * host_mass / distance.powi(3) * (3.0 * d.dot(e) * e - d)
+            }
+        }
+    }
+}
+
+fn softened_pull(g: f32, mass: f32, r: Vec2, softening: f32) -> Vec2 {
+    let dist2 = r.length_squared() + softening * softening;
+    if dist2 == 0.0 {
+        return Vec2::ZERO;
+    }
+    g * mass * r / dist2.powf(1.5)
+}
+
+/// World-space fields active for the current run; rebuilt from the scenario on reset.
+#[derive(Resource, Default, Clone)]
+pub struct ExternalFields(pub Vec<ExternalField>);
+
+pub(super) fn apply_external_fields(
+    settings: Res<SimSettings>,
+    clock: Res<SimClock>,
+    fields: Res<ExternalFields>,
+    sources: Query<(&ExternalField, &Transform), Without<Body>>,
+    mut q: Query<(&mut Body, &Transform)>,
+) {
+    if !settings.running || (fields.0.is_empty() && sources.is_empty()) {
+        return;
+    }
+    let time = clock.elapsed;
+    let attached: Vec<(ExternalField, Vec2)> = sources
+        .iter()
+        .map(|(f, t)| (*f, t.translation.truncate()))
+        .collect();
+
+    for (mut b, t) in &mut q {
+        let pos = t.translation.truncate();
+        let mut acc = Vec2::ZERO;
+        for f in &fields.0 {
+            acc += f.acceleration(settings.g, pos, time);
+        }
+        for (f, origin) in &attached {
+            acc += f.acceleration(settings.g, pos - *origin, time);
+        }
+        b.acc += acc;
+    }
+}
@@ -0,0 +1,168 @@
+//! Pluggable pairwise force laws for the Barnes–Hut solver.
+//!
+//! A [`ForceLaw`] supplies the kernel for a single source (`pair`), the
+//! monopole approximation used for distant tree nodes (`far_field`) and an
+//! optional non-linear correction of the summed acceleration (`finish`).
+//! The active law is chosen by `SimSettings::force_law`; a custom law can be
+//! handed to the app through `SimPlugin::force_law` and selected as `ForceLawKind::Custom`.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::SimSettings;
+
+pub trait ForceLaw: Send + Sync + 'static {
+    /// Acceleration towards a point `mass` at offset `r` (source minus target),
+    /// with the solver's squared softening `soft2`.
+    fn pair(&self, r: Vec2, mass: f32, g: f32, soft2: f32) -> Vec2;
+
+    /// Acceleration from a whole tree node of edge length `size` whose centre of
+    /// mass sits at offset `r`. Defaults to treating the node as a point mass.
+    fn far_field(&self, r: Vec2, mass: f32, g: f32, soft2: f32, size: f32) -> Vec2 {
+        let _ = size;
+        self.pair(r, mass, g, soft2)
+    }
+
+    /// Adjusts the summed acceleration on one body (identity for linear laws).
+    fn finish(&self, acc: Vec2) -> Vec2 {
+        acc
+    }
+
+    fn name(&self) -> &'static str;
+}
+
+/// The inverse-square law with the solver's (adaptive) Plummer softening.
+pub struct Newtonian;
+
+impl ForceLaw for Newtonian {
+    fn pair(&self, r: Vec2, mass: f32, g: f32, soft2: f32) -> Vec2 {
+        let dist2 = r.length_squared() + soft2;
+        if dist2 == 0.0 {
+            return Vec2::ZERO;
+        }
+        let inv = 1.0 / dist2.sqrt().powi(3);
+        g * mass * r * inv
+    }
+    fn name(&self) -> &'static str {
+        "Newtonian"
+    }
+}
+
+/// Newtonian gravity with an extra fixed Plummer core of radius `scale` on top of
+/// the solver softening, as if every body were a diffuse cloud.
+pub struct PlummerSoftened {
+    pub scale: f32,
+}
+
+impl ForceLaw for PlummerSoftened {
+    fn pair(&self, r: Vec2, mass: f32, g: f32, soft2: f32) -> Vec2 {
+        Newtonian.pair(r, mass, g, soft2 + self.scale * self.scale)
+    }
+    fn name(&self) -> &'static str {
+        "Plummer-softened"
+    }
+}
+
+/// Screened gravity, Φ = −G·m·e^(−r/λ)/r: Newtonian up close, vanishing beyond a few `length`s.
+pub struct Yukawa {
+    pub length: f32,
+}
+
+impl ForceLaw for Yukawa {
+    fn pair(&self, r: Vec2, mass: f32, g: f32, soft2: f32) -> Vec2 {
+        let d = (r.length_squared() + soft2).sqrt();
+        if d == 0.0 {
+            return Vec2::ZERO;
+        }
+        let lambda = self.length.max(1e-3);
+        let magnitude = g * mass * (-d / lambda).exp() * (1.0 / (d * d) + 1.0 / (lambda * d));
+        r / d * magnitude
+    }
+    fn name(&self) -> &'static str {
+        "Yukawa"
+    }
+}
+
+/// MOND-like gravity: Newtonian sums, boosted by the "simple" interpolating
+/// function ν(y) = ½ + √(¼ + 1/y) once accelerations fall below `a0`.
+pub struct Mond {
+    pub a0: f32,
+}
+
+impl ForceLaw for Mond {
+    fn pair(&self, r: Vec2, mass: f32, g: f32, soft2: f32) -> Vec2 {
+        Newtonian.pair(r, mass, g, soft2)
+    }
+    fn finish(&self, acc: Vec2) -> Vec2 {
+        let an = acc.length();
+        if an == 0.0 || self.a0 <= 0.0 {
+            return acc;
+        }
+        let y = an / self.a0;
+        acc * (0.5 + (0.25 + 1.0 / y).sqrt())
+    }
+    fn name(&self) -> &'static str {
+        "MOND"
+    }
+}
+
+/// Law selection as stored in `SimSettings` (and therefore in scenarios).
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum ForceLawKind {
+    #[default]
+    Newtonian,
+    PlummerSoftened {
+        scale: f32,
+    },
+    Yukawa {
+        length: f32,
+    },
+    Mond {
+        a0: f32,
+    },
+    /// The law given as `SimPlugin::force_law` (Newtonian if none was given).
+    Custom,
+}
+
+/// Law supplied by the embedding app through `SimPlugin`.
+#[derive(Resource, Default, Clone)]
+pub struct CustomForceLaw(pub Option<Arc<dyn ForceLaw>>);
+
+/// The law `apply_bh_forces` uses this frame.
+#[derive(Resource, Clone)]
+pub struct ActiveForceLaw {
+    pub kind: ForceLawKind,
+    pub law: Arc<dyn ForceLaw>,
+}
+
+impl Default for ActiveForceLaw {
+    fn default() -> Self {
+        Self {
+            kind: ForceLawKind::Newtonian,
+            law: Arc::new(Newtonian),
+        }
+    }
+}
+
+fn build(kind: ForceLawKind, custom: &CustomForceLaw) -> Arc<dyn ForceLaw> {
+    match kind {
+        ForceLawKind::Newtonian => Arc::new(Newtonian),
+        ForceLawKind::PlummerSoftened { scale } => Arc::new(PlummerSoftened { scale }),
+        ForceLawKind::Yukawa { length } => Arc::new(Yukawa { length }),
+        ForceLawKind::Mond { a0 } => Arc::new(Mond { a0 }),
+        ForceLawKind::Custom => custom.0.clone().unwrap_or_else(|| Arc::new(Newtonian)),
+    }
+}
+
+/// Rebuilds the active law whenever the selection in `SimSettings` changes.
+pub(super) fn sync_force_law(
+    settings: Res<SimSettings>,
+    custom: Res<CustomForceLaw>,
+    mut active: ResMut<ActiveForceLaw>,
+) {
+    if active.kind != settings.force_law || custom.is_changed() {
+        active.kind = settings.force_law;
+        active.law = build(settings.force_law, &custom);
+    }
+}
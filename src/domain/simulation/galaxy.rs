@@ -0,0 +1,167 @@
+//! Galaxy-scale generator: exponential disk with spiral arms, a central
+//! supermassive black hole, and a static analytic dark-matter halo.
+//!
+//! The halo is not made of bodies; it is registered as an external field (see
+//! `fields`) when the galaxy spawns, and disk velocities are set from the full
+//! rotation curve (centre + enclosed disk + halo) so the disk starts in rotational balance.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use super::initial_conditions::{gaussian, BodyInit};
+
+/// Static spherical dark-matter halo centred on the origin.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum HaloModel {
+    None,
+    /// Navarro–Frenk–White profile; `mass` is the characteristic mass 4πρₛrₛ³.
+    Nfw {
+        mass: f32,
+        scale_radius: f32,
+    },
+}
+
+impl HaloModel {
+    /// Squared circular speed the halo alone supports at radius `r`.
+    pub fn circular_speed_sq(&self, g: f32, r: f32) -> f32 {
+        if r <= 0.0 {
+            return 0.0;
+        }
+        match *self {
+            HaloModel::None => 0.0,
+            HaloModel::Nfw { mass, scale_radius } => {
+                // ln(1 + x) − x/(1 + x) cancels to nothing in f32 near the
+                // centre, leaving rounding noise that flings the core apart.
+                let x = (r / scale_radius.max(1.0)) as f64;
+                let enclosed = mass as f64 * (x.ln_1p() - x / (1.0 + x));
+                (g as f64 * enclosed / r as f64) as f32
+            }
+        }
+    }
+
+    /// Acceleration at `pos` (relative to the halo centre).
+    pub fn acceleration(&self, g: f32, pos: Vec2) -> Vec2 {
+        let r = pos.length();
+        if r <= 0.0 {
+            return Vec2::ZERO;
+        }
+        -pos / r * (self.circular_speed_sq(g, r) / r)
+    }
+}
+
+/// Parameters for [`generate`].
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GalaxySpec {
+    pub disk_count: usize,
+    pub disk_mass: f32,
+    /// Exponential scale length R_d of the disk.
+    pub disk_scale_length: f32,
+    /// Disk truncation radius in units of R_d.
+    pub disk_cutoff: f32,
+    pub arms: u32,
+    /// Pitch angle of the logarithmic spiral arms (degrees).
+    pub arm_pitch_deg: f32,
+    /// Density contrast of the arms, 0 (no arms) to 1 (empty inter-arm regions).
+    pub arm_amplitude: f32,
+    /// Mass of the central body (massive enough to be a `BlackHole`).
+    pub centre_mass: f32,
+    pub halo: HaloModel,
+    /// Gaussian velocity scatter as a fraction of the local circular speed.
+    pub dispersion: f32,
+    /// Samples the galaxy from its own generator, so it comes out the same
+    /// whatever the run's seed; `None` draws from the caller's stream.
+    pub seed: Option<u64>,
+}
+
+impl Default for GalaxySpec {
+    fn default() -> Self {
+        Self {
+            disk_count: 3000,
+            disk_mass: 3e5,
+            disk_scale_length: 900.0,
+            disk_cutoff: 4.0,
+            arms: 2,
+            arm_pitch_deg: 18.0,
+            arm_amplitude: 0.6,
+            centre_mass: 2e6,
+            halo: HaloModel::Nfw {
+                mass: 1e7,
+                scale_radius: 3000.0,
+            },
+            dispersion: 0.05,
+            seed: None,
+        }
+    }
+}
+
+/// Fraction of an exponential disk's mass inside `x = R / R_d`.
+fn disk_mass_fraction(x: f32) -> f32 {
+    1.0 - (1.0 + x) * (-x).exp()
+}
+
+/// Inverts [`disk_mass_fraction`] by bisection over [0, cutoff].
+fn sample_disk_radius(u: f32, cutoff: f32) -> f32 {
+    let target = u * disk_mass_fraction(cutoff);
+    let (mut lo, mut hi) = (0.0_f32, cutoff);
+    for _ in 0..40 {
+        let mid = 0.5 * (lo + hi);
+        if disk_mass_fraction(mid) < target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Samples the galaxy, centred on the origin and rotating counter-clockwise.
+pub fn generate<R: Rng + ?Sized>(g: f32, spec: &GalaxySpec, rng: &mut R) -> Vec<BodyInit> {
+    match spec.seed {
+        Some(seed) => sample_galaxy(g, spec, &mut ChaCha8Rng::seed_from_u64(seed)),
+        None => sample_galaxy(g, spec, rng),
+    }
+}
+
+fn sample_galaxy<R: Rng + ?Sized>(g: f32, spec: &GalaxySpec, rng: &mut R) -> Vec<BodyInit> {
+    let mut out: Vec<BodyInit> = Vec::with_capacity(spec.disk_count + 1);
+
+    out.push(BodyInit {
+        pos: Vec2::ZERO,
+        vel: Vec2::ZERO,
+        mass: spec.centre_mass,
+    });
+    let rd = spec.disk_scale_length.max(1.0);
+    let cutoff = spec.disk_cutoff.max(0.5);
+    let disk_total = spec.disk_mass;
+    let circular_speed = |r: f32| {
+        let enclosed =
+            spec.centre_mass + disk_total * disk_mass_fraction(r / rd) / disk_mass_fraction(cutoff);
+        (g * enclosed / r.max(1.0) + spec.halo.circular_speed_sq(g, r)).sqrt()
+    };
+
+    let m = disk_total / spec.disk_count.max(1) as f32;
+    let pitch = spec.arm_pitch_deg.to_radians().tan().max(1e-3);
+    let amp = spec.arm_amplitude.clamp(0.0, 1.0);
+    for _ in 0..spec.disk_count {
+        let r = sample_disk_radius(rng.gen(), cutoff) * rd;
+        // Logarithmic spiral: accept angles near the arm crest more often.
+        let phase = (r / rd).max(1e-3).ln() / pitch;
+        let ang = loop {
+            let ang = rng.gen::<f32>() * TAU;
+            let weight = 1.0 + amp * (spec.arms as f32 * (ang - phase)).cos();
+            if spec.arms == 0 || rng.gen::<f32>() * (1.0 + amp) <= weight {
+                break ang;
+            }
+        };
+        let pos = Vec2::from_angle(ang) * r;
+        let vc = circular_speed(r);
+        let vel = pos.normalize_or_zero().perp() * vc
+            + Vec2::new(gaussian(rng), gaussian(rng)) * vc * spec.dispersion;
+        out.push(BodyInit { pos, vel, mass: m });
+    }
+    out
+}
@@ -0,0 +1,175 @@
+//! Ghost runs.
+//!
+//! A replay with a player track can be raced as a ghost. Loading one resets
+//! the world to the replay's scenario, seed and settings, and a translucent
+//! copy of the recorded player then follows the track by sim time since the
+//! last reset, so a retry restarts the race and rewinding or loading a
+//! snapshot moves the ghost with the clock. The ghost is only a sprite: it has
+//! no [`Body`] and nothing in the simulation sees it.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use super::nebula::Nebula;
+use super::replay::{Replay, ReplayFile, ReplayMode, TrackSample};
+use super::{reset_world, Body, Class, PlayerInput, SimClock, SimSettings, SimStats};
+
+/// Marks the ghost's sprite.
+#[derive(Component)]
+pub struct GhostSprite;
+
+#[derive(Event, Clone, Debug)]
+pub enum GhostCommand {
+    Load(PathBuf),
+    Clear,
+}
+
+pub struct GhostRun {
+    pub path: PathBuf,
+    track: Vec<TrackSample>,
+    sprite: Entity,
+}
+
+impl GhostRun {
+    /// Sim seconds the recorded run lasted.
+    pub fn duration(&self) -> f32 {
+        self.track.last().map_or(0.0, |s| s.time)
+    }
+
+    /// The ghost `time` seconds into the run, interpolated between samples;
+    /// `None` once its track has ended.
+    pub fn at(&self, time: f32) -> Option<TrackSample> {
+        let next = self.track.partition_point(|s| s.time <= time);
+        if next == 0 {
+            return self.track.first().copied();
+        }
+        let before = &self.track[next - 1];
+        let Some(after) = self.track.get(next) else {
+            return (time <= before.time).then_some(*before);
+        };
+        let t = ((time - before.time) / (after.time - before.time)).clamp(0.0, 1.0);
+        Some(TrackSample {
+            time,
+            pos: before.pos.lerp(after.pos, t),
+            mass: before.mass + (after.mass - before.mass) * t,
+            class: before.class,
+            score: before.score + (after.score - before.score) * t,
+        })
+    }
+
+    /// The last recorded sample.
+    pub fn finish(&self) -> Option<&TrackSample> {
+        self.track.last()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Ghost {
+    pub run: Option<GhostRun>,
+    /// Outcome of the last load, for the HUD.
+    pub status: Option<String>,
+}
+
+fn spawn_ghost_sprite(commands: &mut Commands) -> Entity {
+    commands
+        .spawn((
+            GhostSprite,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgba(0.7, 0.9, 1.0, 0.35),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .id()
+}
+
+/// Loads and clears ghosts. Loading is refused while a replay records or
+/// plays, since its reset would not be part of the log.
+pub(super) fn handle_commands(
+    mut commands: Commands,
+    mut ghost: ResMut<Ghost>,
+    mut ev_ghost: EventReader<GhostCommand>,
+    replay: Res<Replay>,
+    mut input: ResMut<PlayerInput>,
+    mut settings: ResMut<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    doomed: Query<Entity, Or<(With<Body>, With<Nebula>)>>,
+) {
+    let Some(command) = ev_ghost.read().last().cloned() else {
+        return;
+    };
+    if let Some(old) = ghost.run.take() {
+        commands.entity(old.sprite).despawn();
+    }
+    ghost.status = None;
+    let GhostCommand::Load(path) = command else {
+        return;
+    };
+    if !matches!(replay.mode, ReplayMode::Idle) {
+        ghost.status = Some("Stop the current replay before loading a ghost".into());
+        return;
+    }
+    let file = match ReplayFile::read(&path) {
+        Ok(file) if file.track.is_empty() => {
+            ghost.status = Some(format!("{} has no player track", path.display()));
+            return;
+        }
+        Ok(file) => file,
+        Err(e) => {
+            ghost.status = Some(format!("Could not read {}: {e}", path.display()));
+            return;
+        }
+    };
+
+    let display = settings.clone();
+    let deterministic = settings.deterministic;
+    settings.scenario = file.header.scenario;
+    reset_world(
+        &mut commands,
+        &doomed,
+        &mut stats,
+        &mut settings,
+        file.header.seed,
+    );
+    *settings = file.settings.clone();
+    settings.copy_display(&display);
+    settings.deterministic = deterministic;
+    *input = PlayerInput::default();
+    ghost.run = Some(GhostRun {
+        path,
+        track: file.track,
+        sprite: spawn_ghost_sprite(&mut commands),
+    });
+}
+
+/// Drops the ghost; the viewer shows a different run.
+pub(super) fn clear(mut commands: Commands, mut ghost: ResMut<Ghost>) {
+    if let Some(run) = ghost.run.take() {
+        commands.entity(run.sprite).despawn();
+    }
+}
+
+/// Moves the ghost's sprite along its track, hiding it once the track ends.
+pub(super) fn follow_ghost(
+    ghost: Res<Ghost>,
+    clock: Res<SimClock>,
+    mut sprites: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<GhostSprite>>,
+) {
+    let Some(run) = &ghost.run else {
+        return;
+    };
+    let Ok((mut transform, mut sprite, mut visibility)) = sprites.get_mut(run.sprite) else {
+        return;
+    };
+    match run.at(clock.elapsed) {
+        Some(sample) => {
+            transform.translation = sample.pos.extend(0.5);
+            sprite.custom_size = Some(Vec2::splat(Class::radius_for_mass(sample.mass)));
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
@@ -0,0 +1,206 @@
+//! Initial-condition generators for orbiting populations.
+//!
+//! Velocities come from the mass actually enclosed by each orbit and the live
+//! `SimSettings::g`, so belts start close to equilibrium whatever the scenario.
+//! Multiple attractors (binaries) are treated as a single mass at their barycentre.
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// A massive body the generated population orbits.
+#[derive(Clone, Copy, Debug)]
+pub struct Attractor {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub mass: f32,
+}
+
+/// One annulus of small bodies.
+#[derive(Clone, Copy, Debug)]
+pub struct BeltSpec {
+    /// Mean orbital radius, measured from the barycentre of the attractors.
+    pub radius: f32,
+    /// Full radial width; positions are spread uniformly across it.
+    pub width: f32,
+    pub count: usize,
+    pub mass_range: (f32, f32),
+    /// Eccentricity of every orbit; phases are randomised so the belt stays annular.
+    pub eccentricity: f32,
+    /// Gaussian velocity scatter as a fraction of the local circular speed.
+    pub dispersion: f32,
+}
+
+/// The central pair of `SystemType::BinaryStar`; heavy enough members are
+/// black holes rather than stars.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinarySpec {
+    pub primary_mass: f32,
+    pub secondary_mass: f32,
+    /// Separation at periapsis.
+    pub separation: f32,
+    pub eccentricity: f32,
+}
+
+impl Default for BinarySpec {
+    fn default() -> Self {
+        Self {
+            primary_mass: 4e5,
+            secondary_mass: 2e5,
+            separation: 600.0,
+            eccentricity: 0.0,
+        }
+    }
+}
+
+/// Position, velocity and mass of a body to spawn.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyInit {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub mass: f32,
+}
+
+/// Speed of a circular orbit of radius `r` around `enclosed_mass`.
+pub fn circular_speed(g: f32, enclosed_mass: f32, r: f32) -> f32 {
+    if r <= 0.0 {
+        return 0.0;
+    }
+    (g * enclosed_mass.max(0.0) / r).sqrt()
+}
+
+/// Velocity of a counter-clockwise Keplerian orbit that passes through `rel_pos` with
+/// eccentricity `e`, at true anomaly `anomaly`.
+pub fn orbital_velocity(g: f32, enclosed_mass: f32, rel_pos: Vec2, e: f32, anomaly: f32) -> Vec2 {
+    let r = rel_pos.length();
+    if r <= 0.0 {
+        return Vec2::ZERO;
+    }
+    let e = e.clamp(0.0, 0.99);
+    // Semi-latus rectum of the ellipse that puts the body at distance r at this anomaly.
+    let p = r * (1.0 + e * anomaly.cos());
+    let vk = (g * enclosed_mass.max(0.0) / p).sqrt();
+    let radial = rel_pos / r;
+    let tangential = radial.perp();
+    radial * (vk * e * anomaly.sin()) + tangential * (vk * (1.0 + e * anomaly.cos()))
+}
+
+/// Total mass, barycentre position and barycentre velocity of `attractors`.
+pub fn barycentre(attractors: &[Attractor]) -> Attractor {
+    let mass: f32 = attractors.iter().map(|a| a.mass).sum();
+    if mass <= 0.0 {
+        return Attractor {
+            pos: Vec2::ZERO,
+            vel: Vec2::ZERO,
+            mass: 0.0,
+        };
+    }
+    let pos = attractors.iter().map(|a| a.pos * a.mass).sum::<Vec2>() / mass;
+    let vel = attractors.iter().map(|a| a.vel * a.mass).sum::<Vec2>() / mass;
+    Attractor { pos, vel, mass }
+}
+
+/// Two stars on a Keplerian orbit of the given separation (at periapsis) about a
+/// barycentre at `centre`, moving counter-clockwise.
+pub fn binary_pair(
+    g: f32,
+    m1: f32,
+    m2: f32,
+    separation: f32,
+    eccentricity: f32,
+    centre: Vec2,
+) -> [Attractor; 2] {
+    let total = m1 + m2;
+    let e = eccentricity.clamp(0.0, 0.99);
+    let rel_speed = (g * total * (1.0 + e) / separation).sqrt();
+    let r1 = separation * m2 / total;
+    let r2 = separation * m1 / total;
+    [
+        Attractor {
+            pos: centre + Vec2::new(-r1, 0.0),
+            vel: Vec2::new(0.0, -rel_speed * m2 / total),
+            mass: m1,
+        },
+        Attractor {
+            pos: centre + Vec2::new(r2, 0.0),
+            vel: Vec2::new(0.0, rel_speed * m1 / total),
+            mass: m2,
+        },
+    ]
+}
+
+/// Standard normal sample (Box–Muller).
+pub fn gaussian<R: Rng + ?Sized>(rng: &mut R) -> f32 {
+    let u1 = rng.gen::<f32>().max(f32::MIN_POSITIVE);
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+/// Generates every belt around `attractors`.
+///
+/// Each body's orbital speed uses the attractors' mass plus the mass of every
+/// generated body at a smaller radius, so outer belts feel the inner ones.
+/// `push` gives the coefficient of any outward `c / r²` acceleration on a body
+/// of the given mass (radiation pressure), which is taken off that pull.
+pub fn generate_belts<R: Rng + ?Sized>(
+    g: f32,
+    attractors: &[Attractor],
+    belts: &[BeltSpec],
+    push: impl Fn(f32) -> f32,
+    rng: &mut R,
+) -> Vec<BodyInit> {
+    let centre = barycentre(attractors);
+
+    // Sample positions and masses first; velocities need the enclosed mass profile.
+    struct Sample {
+        rel: Vec2,
+        mass: f32,
+        belt: usize,
+    }
+    let mut samples: Vec<Sample> = Vec::new();
+    for (i, belt) in belts.iter().enumerate() {
+        for _ in 0..belt.count {
+            let ang = rng.gen::<f32>() * TAU;
+            let r = belt.radius + (rng.gen::<f32>() - 0.5) * belt.width;
+            let (lo, hi) = belt.mass_range;
+            let mass = if hi > lo { rng.gen_range(lo..hi) } else { lo };
+            samples.push(Sample {
+                rel: Vec2::from_angle(ang) * r.max(1.0),
+                mass,
+                belt: i,
+            });
+        }
+    }
+
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    order.sort_by(|&a, &b| {
+        samples[a]
+            .rel
+            .length_squared()
+            .total_cmp(&samples[b].rel.length_squared())
+    });
+
+    let mut out: Vec<BodyInit> = Vec::with_capacity(samples.len());
+    let mut enclosed = centre.mass;
+    for idx in order {
+        let s = &samples[idx];
+        let belt = &belts[s.belt];
+        let r = s.rel.length();
+        let anomaly = rng.gen::<f32>() * TAU;
+        let pull = enclosed - push(s.mass) / g.max(1e-6);
+        let mut vel = orbital_velocity(g, pull, s.rel, belt.eccentricity, anomaly);
+        if belt.dispersion > 0.0 {
+            let sigma = belt.dispersion * circular_speed(g, pull, r);
+            vel += Vec2::new(gaussian(rng), gaussian(rng)) * sigma;
+        }
+        out.push(BodyInit {
+            pos: centre.pos + s.rel,
+            vel: centre.vel + vel,
+            mass: s.mass,
+        });
+        enclosed += s.mass;
+    }
+    out
+}
@@ -1,17 +1,70 @@
 use bevy::color::LinearRgba;
+use bevy::ecs::schedule::ScheduleLabel;
 use bevy::prelude::*;
-use rand::{Rng, RngCore, SeedableRng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-
+use std::sync::Arc;
+
+pub mod accretion;
+pub mod autosave;
+pub mod capture;
+pub mod cluster;
+pub mod crash;
+pub mod fields;
+pub mod force_law;
+pub mod galaxy;
+pub mod ghost;
+pub mod initial_conditions;
+pub mod nebula;
+pub mod orbits;
+pub mod post_newtonian;
 mod quadtree;
-
+pub mod radiation;
+pub mod regularization;
+pub mod replay;
+pub mod rewind;
+pub mod rng;
+pub mod satellites;
+pub mod snapshot;
+pub mod sph;
+pub mod state_hash;
+pub mod structure;
+pub mod viewer;
+
+use accretion::{AccretionSettings, AccretionStats, DiskParticle};
+use autosave::Autosave;
+use capture::{CaptureBuffer, ExportCapture};
+use cluster::{ClusterModel, ClusterSpec, Imf};
+use crash::{CrashLog, CrashReport};
+use fields::{ExternalField, ExternalFields};
+use force_law::{ActiveForceLaw, CustomForceLaw, ForceLaw, ForceLawKind};
+use galaxy::{GalaxySpec, HaloModel};
+use ghost::{Ghost, GhostCommand};
+use initial_conditions::{Attractor, BeltSpec, BinarySpec, BodyInit};
+use nebula::NebulaSpec;
+use orbits::Orbits;
+use post_newtonian::{BlackHoleMerger, PostNewtonianSettings};
 use quadtree::{Quad, QuadTree};
+use radiation::RadiationSettings;
+use regularization::{RegularizationSettings, RegularizedPairs};
+use replay::{Replay, ReplayCommand};
+use rewind::Rewind;
+use rng::{RngStream, SimRng};
+use satellites::{SatelliteCaptured, SatelliteLost, SatelliteSettings, SatelliteTracks};
+use snapshot::{LoadSnapshot, SaveSnapshot, SnapshotStatus};
+use sph::{GasParticle, SphSettings};
+use state_hash::StateHash;
+use structure::{StructureSettings, Structures};
+use viewer::Viewer;
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub enum AppState {
     #[default]
     Playing,
     GameOver,
+    /// Watching a replay in the viewer; the player has no control.
+    Viewing,
 }
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
@@ -21,15 +74,18 @@ pub enum SimState {
     Sequential,
 }
 
-#[derive(Resource)]
-pub struct SeededRng(pub rand::rngs::StdRng);
-
 #[derive(Resource)]
 struct TrailSpawnTimer(Timer);
 
 #[derive(Resource)]
 struct HazardSpawnTimer(Timer);
 
+impl Default for HazardSpawnTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(15.0, TimerMode::Repeating))
+    }
+}
+
 #[derive(Event)]
 struct BodyAbsorbed {
     winner: Entity,
@@ -38,14 +94,14 @@ struct BodyAbsorbed {
     loser_class: Class,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub enum Objective {
     #[default]
     None,
     Survive,
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
 pub struct Mission {
     pub objective: Objective,
     pub progress: f32,
@@ -64,58 +120,205 @@ impl Default for Mission {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub enum SystemType {
     #[default]
     SingleStar,
     BinaryStar,
     Cluster,
+    Galaxy,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub enum Scenario {
     #[default]
     CalmBelts,
     BinaryMayhem,
     StarNursery,
     BHArena,
+    SpiralGalaxy,
+}
+
+#[derive(Default)]
+pub struct SimPlugin {
+    /// Optional force law selectable as `ForceLawKind::Custom` (and selected at startup).
+    pub force_law: Option<Arc<dyn ForceLaw>>,
 }
 
-pub struct SimPlugin;
 impl Plugin for SimPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<SimSettings>()
+        let mut settings = SimSettings::default();
+        if self.force_law.is_some() {
+            settings.force_law = ForceLawKind::Custom;
+        }
+        app.insert_resource(settings)
+            .insert_resource(CustomForceLaw(self.force_law.clone()))
+            .init_resource::<ActiveForceLaw>()
             .init_resource::<SimStats>()
             .init_resource::<Mission>()
+            .init_resource::<Orbits>()
+            .init_resource::<SimClock>()
+            .init_resource::<ExternalFields>()
+            .init_resource::<SpatialHash>()
+            .init_resource::<AccretionStats>()
+            .init_resource::<Structures>()
+            .init_resource::<SatelliteTracks>()
+            .init_resource::<RegularizedPairs>()
+            .init_resource::<SimRng>()
+            .init_resource::<NextBodyId>()
+            .init_resource::<StateHash>()
+            .init_resource::<SnapshotStatus>()
+            .init_resource::<PlayerInput>()
+            .init_resource::<Replay>()
+            .init_resource::<CaptureBuffer>()
+            .init_resource::<Rewind>()
+            .init_resource::<Viewer>()
+            .init_resource::<Ghost>()
+            .init_resource::<CrashLog>()
+            .init_resource::<CrashReport>()
+            .init_resource::<Autosave>()
             .insert_resource(TrailSpawnTimer(Timer::from_seconds(
                 0.05,
                 TimerMode::Repeating,
             )))
-            .insert_resource(HazardSpawnTimer(Timer::from_seconds(
-                15.0,
-                TimerMode::Repeating,
-            )))
+            .init_resource::<HazardSpawnTimer>()
             .add_event::<SpawnBurst>()
             .add_event::<PlayerDied>()
             .add_event::<ResetEvent>()
+            .add_event::<BurstRequest>()
             .add_event::<BodyAbsorbed>()
-            .add_systems(Startup, (spawn_initial_bodies, spawn_player))
-            .add_systems(Update, (handle_reset, update_mission, player_death_system))
-            .add_systems(OnEnter(SimState::Sequential), |mut commands: Commands| {
-                commands.insert_resource(SeededRng(rand::SeedableRng::from_seed([0; 32])));
-            })
-            .add_systems(OnExit(SimState::Sequential), |mut commands: Commands| {
-                commands.remove_resource::<SeededRng>();
-            })
+            .add_event::<BlackHoleMerger>()
+            .add_event::<SatelliteCaptured>()
+            .add_event::<SatelliteLost>()
+            .add_event::<SaveSnapshot>()
+            .add_event::<LoadSnapshot>()
+            .add_event::<ReplayCommand>()
+            .add_event::<ExportCapture>()
+            .add_event::<GhostCommand>()
+            .configure_sets(
+                Update,
+                (SimPhase::Input, SimPhase::Apply, SimPhase::Step).chain(),
+            )
+            .configure_sets(
+                SimTick,
+                (TickPhase::Input, TickPhase::Physics, TickPhase::Finish).chain(),
+            )
+            .add_systems(
+                Startup,
+                (
+                    nebula::init_nebula_texture,
+                    spawn_initial_bodies,
+                    crash::install_panic_hook,
+                    crash::find_crash_report,
+                    autosave::find_resume,
+                ),
+            )
+            .add_systems(
+                Update,
+                autosave::autosave
+                    .after(SimPhase::Step)
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(Last, autosave::save_on_exit)
+            .add_systems(
+                Update,
+                (
+                    nebula::style_new_nebulae,
+                    sph::style_new_gas,
+                    sph::update_gas_render,
+                ),
+            )
+            .add_systems(
+                Update,
+                player_death_system.run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    viewer::seek.run_if(in_state(AppState::Viewing)),
+                    replay::handle_commands.run_if(simulating),
+                    ghost::handle_commands.run_if(in_state(AppState::Playing)),
+                    // Rewinding also works from the game-over screen.
+                    rewind::scrub,
+                )
+                    .chain()
+                    .in_set(SimPhase::Apply),
+            )
+            // Scrubbing shows restored keyframes instead of stepping.
             .add_systems(
                 Update,
+                run_ticks
+                    .in_set(SimPhase::Step)
+                    .run_if(simulating)
+                    .run_if(rewind::not_rewinding),
+            )
+            .add_systems(OnEnter(AppState::Viewing), (viewer::reset, ghost::clear))
+            .add_systems(Update, ghost::follow_ghost.after(SimPhase::Step))
+            .add_systems(OnExit(AppState::Viewing), viewer::reset)
+            .add_systems(SimTick, replay::apply_inputs.in_set(TickPhase::Input))
+            .add_systems(SimTick, update_mission.in_set(TickPhase::Physics))
+            .add_systems(
+                SimTick,
                 (
-                    kick1_drift,
+                    advance_clock,
+                    replay::record_track,
+                    assign_body_ids,
+                    state_hash::hash_state,
+                    capture::record_frame,
+                    rewind::record_keyframe,
+                    crash::record_tick,
+                    crash::record_snapshot,
+                    (viewer::collect_markers, viewer::record_keyframe)
+                        .run_if(in_state(AppState::Viewing)),
+                    snapshot::save_snapshots,
+                    snapshot::load_snapshots,
+                )
+                    .chain()
+                    .in_set(TickPhase::Finish),
+            )
+            // Exports and snapshot loads also work from the game-over screen.
+            .add_systems(Update, capture::export_capture.after(SimPhase::Step))
+            .add_systems(
+                Update,
+                snapshot::load_snapshots.run_if(in_state(AppState::GameOver)),
+            )
+            .add_systems(
+                SimTick,
+                (
+                    (
+                        apply_thrust,
+                        regularization::select_pairs,
+                        kick1_drift,
+                        regularization::drift_pairs,
+                    )
+                        .chain(),
                     rebuild_quadtree,
-                    apply_bh_forces,
-                    kick2,
-                    spatial_hash_build,
-                    resolve_collisions,
+                    // Nested chains rather than `.before(apply_bh_forces)` and the
+                    // like: those systems also live in the sequential set below,
+                    // which makes ordering against them ambiguous.
+                    (
+                        force_law::sync_force_law,
+                        apply_bh_forces,
+                        (
+                            fields::apply_external_fields,
+                            nebula::apply_nebula_drag,
+                            radiation::apply_radiation,
+                            sph::apply_sph,
+                            sph::form_stars,
+                            post_newtonian::apply_post_newtonian,
+                            accretion::update_accretion_disks,
+                        )
+                            .chain(),
+                        (kick2, regularization::kick_pairs).chain(),
+                    )
+                        .chain(),
+                    (
+                        orbits::update_orbits,
+                        structure::detect_structure,
+                        satellites::track_satellites,
+                    )
+                        .chain(),
+                    (spatial_hash_build, resolve_collisions).chain(),
                     update_render,
                     spawn_bursts,
                     spawn_trails,
@@ -124,16 +327,39 @@ impl Plugin for SimPlugin {
                     update_score,
                     spawn_hazards,
                 )
-                    .run_if(in_state(SimState::Parallel))
-                    .run_if(in_state(AppState::Playing)),
+                    .in_set(TickPhase::Physics)
+                    .run_if(in_state(SimState::Parallel)),
             )
             .add_systems(
-                Update,
+                SimTick,
                 (
-                    kick1_drift,
+                    (
+                        apply_thrust,
+                        regularization::select_pairs,
+                        kick1_drift,
+                        regularization::drift_pairs,
+                    )
+                        .chain(),
                     rebuild_quadtree,
+                    force_law::sync_force_law,
                     apply_bh_forces,
-                    kick2,
+                    (
+                        fields::apply_external_fields,
+                        nebula::apply_nebula_drag,
+                        radiation::apply_radiation,
+                        sph::apply_sph,
+                        sph::form_stars,
+                        post_newtonian::apply_post_newtonian,
+                        accretion::update_accretion_disks,
+                    )
+                        .chain(),
+                    (kick2, regularization::kick_pairs).chain(),
+                    (
+                        orbits::update_orbits,
+                        structure::detect_structure,
+                        satellites::track_satellites,
+                    )
+                        .chain(),
                     spatial_hash_build,
                     resolve_collisions,
                     update_render,
@@ -145,13 +371,13 @@ impl Plugin for SimPlugin {
                     spawn_hazards,
                 )
                     .chain()
-                    .run_if(in_state(SimState::Sequential))
-                    .run_if(in_state(AppState::Playing)),
+                    .in_set(TickPhase::Physics)
+                    .run_if(in_state(SimState::Sequential)),
             );
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum Class {
     Asteroid,
     Planet,
@@ -159,14 +385,14 @@ pub enum Class {
     BlackHole,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub enum CollisionMode {
     #[default]
     Absorb,
     Elastic,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub enum ColorPalette {
     #[default]
     Default,
@@ -228,7 +454,7 @@ impl Class {
     }
 }
 
-#[derive(Event)]
+#[derive(Event, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct SpawnBurst {
     pub center: Vec2,
     pub radius: f32,
@@ -243,7 +469,63 @@ pub struct PlayerDied;
 #[derive(Event, Default)]
 pub struct ResetEvent;
 
-#[derive(Resource, Clone)]
+/// A burst the player drew; it becomes a [`SpawnBurst`] at the start of the
+/// next tick, where replays record it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BurstRequest(pub SpawnBurst);
+
+/// Thrust the player is holding, read at the start of every tick.
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Default)]
+pub struct PlayerInput {
+    /// Unnormalized direction; zero means no thrust.
+    pub thrust: Vec2,
+    pub boost: bool,
+}
+
+/// `Update` phases. Controls and the HUD edit settings and player input in
+/// `Input`, `Apply` handles requests that replace the world wholesale (replay
+/// commands, rewinding, viewer seeks), and `Step` runs [`SimTick`].
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimPhase {
+    Input,
+    Apply,
+    Step,
+}
+
+/// One simulation tick. Play runs it once per frame; the replay viewer runs
+/// it as many times as its speed or a seek asks for.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimTick;
+
+/// [`SimTick`] phases. `Input` turns the tick's inputs (live or replayed) into
+/// changes to the world, resets included, `Physics` advances it, and `Finish`
+/// numbers new bodies, hashes the result and handles snapshots once every
+/// other system of the tick is done.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TickPhase {
+    Input,
+    Physics,
+    Finish,
+}
+
+/// Run condition: the world is live, in play or in the replay viewer.
+pub fn simulating(state: Res<State<AppState>>) -> bool {
+    matches!(state.get(), AppState::Playing | AppState::Viewing)
+}
+
+fn run_ticks(world: &mut World) {
+    let ticks = if *world.resource::<State<AppState>>().get() == AppState::Viewing {
+        viewer::ticks_this_frame(world)
+    } else {
+        1
+    };
+    for _ in 0..ticks {
+        world.run_schedule(SimTick);
+    }
+}
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimSettings {
     pub g: f32,
     pub dt: f32,
@@ -257,7 +539,11 @@ pub struct SimSettings {
     pub restitution: f32,
     pub absorb_bias: f32,
     pub collision_mode: CollisionMode,
+    /// Reuse `seed` on reset (instead of drawing a new one) and run the sim
+    /// systems strictly in sequence.
     pub deterministic: bool,
+    /// Seed of the current run; every random decision flows from it through [`SimRng`].
+    pub seed: u64,
     pub follow_player: bool,
     pub time_scale: f32,
     pub show_help: bool,
@@ -270,6 +556,38 @@ pub struct SimSettings {
     pub theta_range: Vec2, // min, max
     pub adaptive_softening: bool,
     pub softening_range: Vec2, // min, max
+    // Belt initial conditions
+    pub belt_eccentricity: f32,
+    pub belt_dispersion: f32, // fraction of circular speed
+    // Central pair (SystemType::BinaryStar)
+    pub binary: BinarySpec,
+    // Star cluster generator (SystemType::Cluster)
+    pub cluster: ClusterSpec,
+    // Galaxy generator and halo potential (SystemType::Galaxy)
+    pub galaxy: GalaxySpec,
+    // Analytic background potentials applied on top of the tree forces
+    pub external_fields: Vec<ExternalField>,
+    pub force_law: ForceLawKind,
+    // Gravity + electrostatics mode
+    pub electrostatics: bool,
+    pub coulomb_k: f32,
+    pub burst_charge: f32, // magnitude given to drag-spawned bodies
+    // Gas regions
+    pub nebulae: NebulaSpec,
+    // Starlight and stellar wind
+    pub radiation: RadiationSettings,
+    // SPH gas and star formation
+    pub sph: SphSettings,
+    // Relativistic corrections for black-hole pairs
+    pub post_newtonian: PostNewtonianSettings,
+    // Tidal disruption and accretion disks around black holes
+    pub accretion: AccretionSettings,
+    // Friends-of-friends groups and bound subsystems
+    pub structure: StructureSettings,
+    // Moon capture around the player
+    pub satellites: SatelliteSettings,
+    // Close pairs integrated in Levi-Civita coordinates
+    pub regularization: RegularizationSettings,
 }
 impl Default for SimSettings {
     fn default() -> Self {
@@ -287,6 +605,7 @@ impl Default for SimSettings {
             absorb_bias: 0.03,
             collision_mode: CollisionMode::default(),
             deterministic: false,
+            seed: 0,
             follow_player: true,
             time_scale: 1.0,
             show_help: true,
@@ -298,14 +617,54 @@ impl Default for SimSettings {
             theta_range: Vec2::new(0.4, 1.0),
             adaptive_softening: true,
             softening_range: Vec2::new(2.0, 10.0),
+            belt_eccentricity: 0.0,
+            belt_dispersion: 0.01,
+            binary: BinarySpec::default(),
+            cluster: ClusterSpec::default(),
+            galaxy: GalaxySpec::default(),
+            external_fields: Vec::new(),
+            force_law: ForceLawKind::default(),
+            electrostatics: false,
+            coulomb_k: 50.0,
+            burst_charge: 20.0,
+            nebulae: NebulaSpec::default(),
+            radiation: RadiationSettings::default(),
+            sph: SphSettings::default(),
+            post_newtonian: PostNewtonianSettings::default(),
+            accretion: AccretionSettings::default(),
+            structure: StructureSettings::default(),
+            satellites: SatelliteSettings::default(),
+            regularization: RegularizationSettings::default(),
         }
     }
 }
 
 impl SimSettings {
+    /// Copies the display-only switches, which never affect the simulation, from `other`.
+    pub fn copy_display(&mut self, other: &SimSettings) {
+        self.show_help = other.show_help;
+        self.show_diagnostics = other.show_diagnostics;
+        self.follow_player = other.follow_player;
+        self.trails_enabled = other.trails_enabled;
+        self.trail_lifespan = other.trail_lifespan;
+        self.color_palette = other.color_palette;
+    }
+
+    /// Softening length where the quadtree's density factor is `density`.
+    pub fn softening_at(&self, density: f32) -> f32 {
+        if self.adaptive_softening {
+            // lerp(min, max, factor)
+            self.softening_range.x + density * (self.softening_range.y - self.softening_range.x)
+        } else {
+            self.softening
+        }
+    }
+
     pub fn from_scenario(scenario: Scenario) -> Self {
-        let mut settings = SimSettings::default();
-        settings.scenario = scenario;
+        let mut settings = SimSettings {
+            scenario,
+            ..default()
+        };
         match scenario {
             Scenario::CalmBelts => {
                 settings.g = 120.0;
@@ -317,6 +676,8 @@ impl SimSettings {
                 settings.collision_mode = CollisionMode::Absorb;
                 settings.restitution = 0.0;
                 settings.absorb_bias = 0.03;
+                // Starlight sweeps the smallest grains out of the inner belt.
+                settings.radiation.enabled = true;
                 settings.trails_enabled = true;
                 settings.trail_lifespan = 1.5;
                 settings.deterministic = false;
@@ -329,6 +690,8 @@ impl SimSettings {
                 settings.theta_range = Vec2::new(0.4, 1.0);
                 settings.adaptive_softening = true;
                 settings.softening_range = Vec2::new(2.0, 10.0);
+                settings.belt_eccentricity = 0.0;
+                settings.belt_dispersion = 0.005;
             }
             Scenario::BinaryMayhem => {
                 settings.g = 200.0;
@@ -352,6 +715,8 @@ impl SimSettings {
                 settings.theta_range = Vec2::new(0.6, 1.2);
                 settings.adaptive_softening = true;
                 settings.softening_range = Vec2::new(5.0, 15.0);
+                settings.belt_eccentricity = 0.08;
+                settings.belt_dispersion = 0.06;
             }
             Scenario::StarNursery => {
                 settings.g = 150.0;
@@ -375,6 +740,30 @@ impl SimSettings {
                 settings.theta_range = Vec2::new(0.5, 1.1);
                 settings.adaptive_softening = true;
                 settings.softening_range = Vec2::new(3.0, 12.0);
+                settings.belt_eccentricity = 0.0;
+                settings.belt_dispersion = 0.02;
+                settings.cluster = ClusterSpec {
+                    model: ClusterModel::King { w0: 5.0 },
+                    imf: Imf::Kroupa,
+                    imf_range: (0.1, 20.0),
+                    count: 120,
+                    total_mass: 2.4e6,
+                    scale_radius: 150.0, // core radius; tidal radius ≈ 10× for W0 = 5
+                    virial_ratio: 0.5,
+                    seed: None,
+                };
+                settings.nebulae = NebulaSpec {
+                    count: 3,
+                    radius_range: (400.0, 900.0),
+                    spread: 1200.0,
+                    peak_density: 0.5,
+                    spin: 0.05,
+                    ..NebulaSpec::default()
+                };
+                settings.sph = SphSettings {
+                    count: 600,
+                    ..SphSettings::default()
+                };
             }
             Scenario::BHArena => {
                 settings.g = 300.0;
@@ -382,7 +771,14 @@ impl SimSettings {
                 settings.softening = 10.0;
                 settings.max_vel = 3000.0;
                 settings.theta = 0.9;
-                settings.system_type = SystemType::SingleStar; // Will spawn a BH as central star
+                settings.system_type = SystemType::BinaryStar;
+                // Two black holes close enough to inspiral and merge.
+                settings.binary = BinarySpec {
+                    primary_mass: 1.2e6,
+                    secondary_mass: 1e6,
+                    separation: 200.0,
+                    eccentricity: 0.0,
+                };
                 settings.collision_mode = CollisionMode::Absorb;
                 settings.restitution = 0.0;
                 settings.absorb_bias = 0.1;
@@ -398,6 +794,40 @@ impl SimSettings {
                 settings.theta_range = Vec2::new(0.7, 1.5);
                 settings.adaptive_softening = true;
                 settings.softening_range = Vec2::new(8.0, 20.0);
+                settings.belt_eccentricity = 0.03;
+                settings.belt_dispersion = 0.03;
+                settings.post_newtonian = PostNewtonianSettings {
+                    enabled: true,
+                    ripple_strength: 1.0,
+                    ..PostNewtonianSettings::default()
+                };
+                settings.accretion = AccretionSettings {
+                    enabled: true,
+                    ..AccretionSettings::default()
+                };
+            }
+            Scenario::SpiralGalaxy => {
+                settings.g = 120.0;
+                settings.dt = 0.008;
+                settings.softening = 12.0;
+                settings.max_vel = 1800.0;
+                settings.theta = 0.8;
+                settings.system_type = SystemType::Galaxy;
+                settings.collision_mode = CollisionMode::Elastic;
+                settings.restitution = 0.5;
+                settings.absorb_bias = 0.0;
+                settings.trails_enabled = false; // thousands of disk bodies
+                settings.trail_lifespan = 1.0;
+                settings.deterministic = false;
+                settings.follow_player = false;
+                settings.time_scale = 1.0;
+                settings.show_help = true;
+                settings.show_diagnostics = false;
+                settings.color_palette = ColorPalette::Default;
+                settings.adaptive_theta = true;
+                settings.theta_range = Vec2::new(0.6, 1.2);
+                settings.adaptive_softening = true;
+                settings.softening_range = Vec2::new(6.0, 16.0);
             }
         }
         settings
@@ -407,6 +837,13 @@ impl SimSettings {
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct SimStats(pub usize);
 
+/// Simulation clock: `tick` counts sim frames, `elapsed` counts sim seconds while running.
+#[derive(Resource, Default, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SimClock {
+    pub tick: u64,
+    pub elapsed: f32,
+}
+
 #[derive(Component)]
 pub struct Body {
     pub mass: f32,
@@ -415,10 +852,22 @@ pub struct Body {
     pub class: Class,
 }
 
-#[derive(Component)]
+/// Stable body identifier, handed out in spawn order at the end of each tick.
+/// Unlike `Entity` it is never recycled, and it restarts from zero on reset.
+#[derive(
+    Component, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize,
+)]
+pub struct BodyId(pub u64);
+
+#[derive(Resource, Default)]
+struct NextBodyId(u64);
+
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Player {
     pub prev_class: Class,
     pub score: f32,
+    /// Captured moons orbiting the player directly (see `satellites`).
+    pub moons: u32,
 }
 
 #[derive(Component)]
@@ -434,6 +883,10 @@ struct SmoothSize {
 #[derive(Component)]
 pub struct Hazard;
 
+/// Electric charge; only felt while `SimSettings::electrostatics` is on.
+#[derive(Component, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Charge(pub f32);
+
 #[derive(Resource)]
 struct TreeState {
     root: Option<QuadTree>,
@@ -448,211 +901,354 @@ impl Default for TreeState {
     }
 }
 
+impl TreeState {
+    /// Grows the bounds to take in every position, with a margin. They only
+    /// grow, but the margin is not compounded tick on tick, or they would run
+    /// off to infinity.
+    fn fit(&mut self, positions: impl IntoIterator<Item = Vec2>) {
+        let max_extent = positions
+            .into_iter()
+            .fold(0.0_f32, |m, p| m.max(p.abs().max_element()));
+        let size = (max_extent * 1.2).max(self.bounds.half_size).max(2000.0);
+        self.bounds = Quad::new(Vec2::ZERO, size);
+    }
+}
+
+/// Softening lengths the first force pass will use at `positions`, were they
+/// the only bodies.
+fn initial_softening(settings: &SimSettings, positions: &[Vec2]) -> Vec<f32> {
+    let mut tree = TreeState::default();
+    tree.fit(positions.iter().copied());
+    let mut qt = QuadTree::new(tree.bounds);
+    for &p in positions {
+        qt.insert(p, 1.0, 0.0);
+    }
+    positions
+        .iter()
+        .map(|&p| settings.softening_at(qt.get_density_factor(p)))
+        .collect()
+}
+
 fn spawn_initial_bodies_inner(
     commands: &mut Commands,
     stats: &mut SimStats,
     settings: &SimSettings,
 ) {
-    commands.insert_resource(TreeState::default());
-
-    let mut rng: Box<dyn RngCore> = if settings.deterministic {
-        Box::new(rand::rngs::StdRng::from_seed([0; 32]))
-    } else {
-        Box::new(rand::thread_rng())
-    };
+    let mut sim_rng = SimRng::new(settings.seed);
+    let rng = sim_rng.stream(RngStream::Generation);
 
+    commands.insert_resource(TreeState::default());
+    commands.insert_resource(SimClock::default());
+    commands.insert_resource(AccretionStats::default());
+    commands.insert_resource(Structures::default());
+    commands.insert_resource(SatelliteTracks::default());
+    commands.insert_resource(NextBodyId::default());
+    commands.insert_resource(HazardSpawnTimer::default());
+
+    commands.insert_resource(external_fields_for(settings));
+
+    // Everything spawned below, for the player's starting orbit.
+    let mut system: Vec<BodyInit> = Vec::new();
+    let mut player_radius: f32 = 340.0;
     match settings.system_type {
         SystemType::SingleStar => {
             // Central star
             let m = 6e5;
-            let class = Class::from_mass(m);
-            commands.spawn((
-                Body {
-                    mass: m,
-                    vel: Vec2::ZERO,
-                    acc: Vec2::ZERO,
-                    class,
-                },
-                SmoothSize {
-                    target_radius: Class::radius_for_mass(m),
-                },
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: class.color(settings.color_palette),
-                        custom_size: Some(Vec2::splat(Class::radius_for_mass(m))),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(Vec3::ZERO),
-                    ..default()
-                },
-            ));
+            spawn_body(commands, settings, Vec2::ZERO, Vec2::ZERO, m);
+            system.push(BodyInit {
+                pos: Vec2::ZERO,
+                vel: Vec2::ZERO,
+                mass: m,
+            });
 
-            // Belts
-            for r in [260.0, 520.0, 980.0, 1600.0] {
-                for _ in 0..500 {
-                    let ang = rng.gen::<f32>() * std::f32::consts::TAU;
-                    let pos = Vec2::from_angle(ang) * (r + rng.gen::<f32>() * 40.0 - 20.0);
-                    let vdir = Vec2::new(-pos.y, pos.x).normalize();
-                    let v = vdir * (pos.length().sqrt() * 3.2);
-                    let mass = rng.gen_range(6.0..60.0);
-                    let class = Class::from_mass(mass);
-                    commands.spawn((
-                        Body {
-                            mass,
-                            vel: v,
-                            acc: Vec2::ZERO,
-                            class,
-                        },
-                        SmoothSize {
-                            target_radius: Class::radius_for_mass(mass),
-                        },
-                        SpriteBundle {
-                            sprite: Sprite {
-                                color: class.color(settings.color_palette),
-                                custom_size: Some(Vec2::splat(Class::radius_for_mass(mass))),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(pos.extend(0.0)),
-                            ..default()
-                        },
-                    ));
-                    stats.0 += 1;
-                }
+            let central = [Attractor {
+                pos: Vec2::ZERO,
+                vel: Vec2::ZERO,
+                mass: m,
+            }];
+            let belts = [260.0, 520.0, 980.0, 1600.0].map(|r| BeltSpec {
+                radius: r,
+                width: 40.0,
+                count: 500,
+                mass_range: (6.0, 60.0),
+                eccentricity: settings.belt_eccentricity,
+                dispersion: settings.belt_dispersion,
+            });
+            for b in initial_conditions::generate_belts(
+                settings.g,
+                &central,
+                &belts,
+                radiation_push(settings, [m]),
+                rng,
+            ) {
+                spawn_body(commands, settings, b.pos, b.vel, b.mass);
+                stats.0 += 1;
+                system.push(b);
             }
         }
         SystemType::BinaryStar => {
-            let m1 = 4e5;
-            let m2 = 2e5;
-            let class1 = Class::from_mass(m1);
-            let class2 = Class::from_mass(m2);
-            let r = 300.0;
-
-            let v1 = (settings.g * m2 / (r * 2.0)).sqrt();
-            let v2 = (settings.g * m1 / (r * 2.0)).sqrt();
-
-            commands.spawn((
-                Body {
-                    mass: m1,
-                    vel: Vec2::new(0.0, v1),
-                    acc: Vec2::ZERO,
-                    class: class1,
-                },
-                SmoothSize {
-                    target_radius: Class::radius_for_mass(m1),
-                },
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: class1.color(settings.color_palette),
-                        custom_size: Some(Vec2::splat(Class::radius_for_mass(m1))),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(Vec3::new(-r, 0.0, 0.0)),
-                    ..default()
-                },
-            ));
-
-            commands.spawn((
-                Body {
-                    mass: m2,
-                    vel: Vec2::new(0.0, -v2),
-                    acc: Vec2::ZERO,
-                    class: class2,
-                },
-                SmoothSize {
-                    target_radius: Class::radius_for_mass(m2),
-                },
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: class2.color(settings.color_palette),
-                        custom_size: Some(Vec2::splat(Class::radius_for_mass(m2))),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(Vec3::new(r, 0.0, 0.0)),
-                    ..default()
-                },
-            ));
+            let spec = settings.binary;
+            let g = settings.g
+                * post_newtonian::circular_orbit_factor(
+                    settings.g,
+                    spec.primary_mass,
+                    spec.secondary_mass,
+                    spec.separation,
+                    &settings.post_newtonian,
+                );
+            let stars = initial_conditions::binary_pair(
+                g,
+                spec.primary_mass,
+                spec.secondary_mass,
+                spec.separation,
+                spec.eccentricity,
+                Vec2::ZERO,
+            );
+            for s in stars {
+                spawn_body(commands, settings, s.pos, s.vel, s.mass);
+                system.push(BodyInit {
+                    pos: s.pos,
+                    vel: s.vel,
+                    mass: s.mass,
+                });
+            }
+            // Between the binary and its belts.
+            player_radius = 1000.0;
+
+            // Circumbinary belts, well outside the binary's unstable zone.
+            let belts = [1500.0, 2100.0].map(|r| BeltSpec {
+                radius: r,
+                width: 60.0,
+                count: 400,
+                mass_range: (6.0, 60.0),
+                eccentricity: settings.belt_eccentricity,
+                dispersion: settings.belt_dispersion,
+            });
+            for b in initial_conditions::generate_belts(
+                settings.g,
+                &stars,
+                &belts,
+                radiation_push(settings, stars.map(|s| s.mass)),
+                rng,
+            ) {
+                spawn_body(commands, settings, b.pos, b.vel, b.mass);
+                stats.0 += 1;
+                system.push(b);
+            }
         }
         SystemType::Cluster => {
-            for _ in 0..50 {
-                let pos = Vec2::new(
-                    rng.gen_range(-1000.0..1000.0),
-                    rng.gen_range(-1000.0..1000.0),
-                );
-                let mass = rng.gen_range(1000.0..50000.0);
-                let class = Class::from_mass(mass);
-                commands.spawn((
-                    Body {
-                        mass,
-                        vel: Vec2::ZERO,
-                        acc: Vec2::ZERO,
-                        class,
-                    },
-                    SmoothSize {
-                        target_radius: Class::radius_for_mass(mass),
-                    },
-                    SpriteBundle {
-                        sprite: Sprite {
-                            color: class.color(settings.color_palette),
-                            custom_size: Some(Vec2::splat(Class::radius_for_mass(mass))),
-                            ..default()
-                        },
-                        transform: Transform::from_translation(pos.extend(0.0)),
-                        ..default()
-                    },
-                ));
+            let softening = |positions: &[Vec2]| initial_softening(settings, positions);
+            for b in cluster::generate(settings.g, softening, &settings.cluster, Vec2::ZERO, rng) {
+                spawn_body(commands, settings, b.pos, b.vel, b.mass);
+                stats.0 += 1;
+                // Clear of the outermost star; the default spawn sits in the core.
+                player_radius = player_radius.max(b.pos.length() * 1.15 + 100.0);
+                system.push(b);
+            }
+        }
+        SystemType::Galaxy => {
+            for b in galaxy::generate(settings.g, &settings.galaxy, rng) {
+                spawn_body(commands, settings, b.pos, b.vel, b.mass);
                 stats.0 += 1;
+                system.push(b);
             }
         }
     }
+
+    let clouds = nebula::spawn_nebulae(commands, &settings.nebulae, rng);
+    let gas = sph::spawn_gas(commands, settings, &clouds, rng);
+    stats.0 += gas.len();
+    system.extend(gas);
+    // Clear of the nebulae too, whose drag would slow the player into a fall.
+    for cloud in &clouds {
+        player_radius = player_radius.max(cloud.centre.length() + 1.5 * cloud.radius + 100.0);
+    }
+    let (pos, vel) = player_orbit(settings, &system, player_radius);
+    spawn_default_player(commands, pos, vel);
+    commands.insert_resource(sim_rng);
+}
+
+/// Start on the +x axis at `radius`, on a circular counter-clockwise orbit
+/// about the mass inside it and any galactic halo, less the starlight's push.
+fn player_orbit(settings: &SimSettings, system: &[BodyInit], radius: f32) -> (Vec2, Vec2) {
+    let inside = || system.iter().filter(|b| b.pos.length() < radius);
+    let enclosed: f32 = inside().map(|b| b.mass).sum();
+    let push = radiation_push(settings, inside().map(|b| b.mass))(PLAYER_START_MASS);
+    let mut speed_sq = (settings.g * enclosed - push).max(0.0) / radius;
+    if settings.system_type == SystemType::Galaxy {
+        speed_sq += settings.galaxy.halo.circular_speed_sq(settings.g, radius);
+    }
+    (Vec2::new(radius, 0.0), Vec2::new(0.0, speed_sq.sqrt()))
+}
+
+/// Outward radiation coefficient, as a function of mass, from stars of the
+/// given masses seen from well outside them.
+fn radiation_push(
+    settings: &SimSettings,
+    masses: impl IntoIterator<Item = f32>,
+) -> impl Fn(f32) -> f32 {
+    let rad = settings.radiation;
+    let light: f32 = masses
+        .into_iter()
+        .map(|m| radiation::luminosity(Class::from_mass(m), m, &rad))
+        .sum();
+    move |m| radiation::pressure_coefficient(light, m, &rad)
+}
+
+/// The scenario's background fields plus the halo of a generated galaxy.
+fn external_fields_for(settings: &SimSettings) -> ExternalFields {
+    let mut fields = settings.external_fields.clone();
+    if settings.system_type == SystemType::Galaxy && settings.galaxy.halo != HaloModel::None {
+        fields.push(ExternalField::Halo {
+            centre: Vec2::ZERO,
+            model: settings.galaxy.halo,
+        });
+    }
+    ExternalFields(fields)
+}
+
+/// Spawns a plain body with its sprite sized and coloured for its class.
+fn spawn_body(
+    commands: &mut Commands,
+    settings: &SimSettings,
+    pos: Vec2,
+    vel: Vec2,
+    mass: f32,
+) -> Entity {
+    let class = Class::from_mass(mass);
+    commands
+        .spawn((
+            Body {
+                mass,
+                vel,
+                acc: Vec2::ZERO,
+                class,
+            },
+            SmoothSize {
+                target_radius: Class::radius_for_mass(mass),
+            },
+            SpriteBundle {
+                sprite: Sprite {
+                    color: class.color(settings.color_palette),
+                    custom_size: Some(Vec2::splat(Class::radius_for_mass(mass))),
+                    ..default()
+                },
+                transform: Transform::from_translation(pos.extend(0.0)),
+                ..default()
+            },
+        ))
+        .id()
 }
 
 pub fn spawn_initial_bodies(
     mut commands: Commands,
     mut stats: ResMut<SimStats>,
-    settings: Res<SimSettings>,
+    mut settings: ResMut<SimSettings>,
 ) {
+    settings.seed = next_seed(&settings);
     spawn_initial_bodies_inner(&mut commands, stats.as_mut(), &settings);
 }
 
-pub fn spawn_player(mut commands: Commands) {
-    let mass = 80.0;
-    let class = Class::from_mass(mass);
-    commands.spawn((
+/// Mass the player starts every scenario with.
+const PLAYER_START_MASS: f32 = 80.0;
+
+fn spawn_default_player(commands: &mut Commands, pos: Vec2, vel: Vec2) {
+    let mass = PLAYER_START_MASS;
+    spawn_player_body(
+        commands,
+        pos,
         Body {
             mass,
-            vel: Vec2::new(0.0, 130.0),
+            vel,
             acc: Vec2::ZERO,
-            class,
-        },
-        SmoothSize {
-            target_radius: Class::radius_for_mass(mass),
+            class: Class::from_mass(mass),
         },
         Player {
-            prev_class: class,
+            prev_class: Class::from_mass(mass),
             score: 0.0,
+            moons: 0,
         },
-        SpriteBundle {
-            sprite: Sprite {
-                color: Color::srgb(0.9, 1.0, 0.9),
-                custom_size: Some(Vec2::splat(Class::radius_for_mass(mass) + 1.5)),
-                ..default()
+    );
+}
+
+/// Spawns the player's body with its highlighted sprite.
+fn spawn_player_body(commands: &mut Commands, pos: Vec2, body: Body, player: Player) -> Entity {
+    let radius = Class::radius_for_mass(body.mass);
+    commands
+        .spawn((
+            body,
+            SmoothSize {
+                target_radius: radius,
             },
-            transform: Transform {
-                translation: Vec3::new(340.0, 0.0, 0.0),
+            player,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(0.9, 1.0, 0.9),
+                    custom_size: Some(Vec2::splat(radius + 1.5)),
+                    ..default()
+                },
+                transform: Transform::from_translation(pos.extend(0.0)),
                 ..default()
             },
-            ..default()
-        },
-    ));
+        ))
+        .id()
+}
+
+fn assign_body_ids(
+    mut commands: Commands,
+    mut next: ResMut<NextBodyId>,
+    q: Query<(Entity, &Body, &Transform), Without<BodyId>>,
+) {
+    // Numbered by state rather than by `Entity`, whose values depend on what was
+    // spawned and despawned before (a replay or a loaded snapshot starts from a
+    // different history). Bodies with identical state are interchangeable.
+    let mut fresh: Vec<(Entity, [u32; 5])> = q
+        .iter()
+        .map(|(e, b, t)| {
+            let key = [t.translation.x, t.translation.y, b.vel.x, b.vel.y, b.mass];
+            (e, key.map(f32::to_bits))
+        })
+        .collect();
+    fresh.sort_by_key(|(_, key)| *key);
+    for (e, _) in fresh {
+        // In the parallel schedule the body may be despawned before this lands.
+        commands.entity(e).try_insert(BodyId(next.0));
+        next.0 += 1;
+    }
 }
 
-fn kick1_drift(settings: Res<SimSettings>, mut q: Query<(&mut Body, &mut Transform)>) {
+/// Applies the held thrust to the player for this tick.
+fn apply_thrust(
+    settings: Res<SimSettings>,
+    input: Res<PlayerInput>,
+    mut players: Query<&mut Body, With<Player>>,
+) {
+    if !settings.running || input.thrust == Vec2::ZERO {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    if let Ok(mut body) = players.get_single_mut() {
+        let boost = if input.boost { 1.75 } else { 1.0 };
+        let acc = input.thrust.normalize() * 380.0 * boost / body.mass.max(1.0);
+        body.vel += acc * dt;
+    }
+}
+
+fn kick1_drift(
+    settings: Res<SimSettings>,
+    pairs: Res<RegularizedPairs>,
+    mut q: Query<(Entity, &mut Body, &mut Transform)>,
+) {
     if !settings.running {
         return;
     }
     let dt = settings.dt * settings.time_scale;
 
-    for (mut b, mut t) in &mut q {
+    for (e, mut b, mut t) in &mut q {
+        // Regularized pairs are advanced by `regularization::drift_pairs`.
+        if pairs.contains(e) {
+            continue;
+        }
         // v_half = v + a * dt/2
         let v_half = b.vel + b.acc * dt * 0.5;
 
@@ -666,17 +1262,37 @@ fn kick1_drift(settings: Res<SimSettings>, mut q: Query<(&mut Body, &mut Transfo
     }
 }
 
-fn rebuild_quadtree(mut tree: ResMut<TreeState>, q: Query<(&Body, &Transform)>) {
-    let mut max_extent = tree.bounds.half_size;
-    for (_, t) in &q {
-        max_extent = max_extent.max(t.translation.truncate().abs().max_element());
-    }
-    let size = (max_extent * 1.2).max(2000.0);
-    tree.bounds = Quad::new(Vec2::ZERO, size);
+fn rebuild_quadtree(
+    mut tree: ResMut<TreeState>,
+    settings: Res<SimSettings>,
+    pairs: Res<RegularizedPairs>,
+    q: Query<(Entity, &Body, &Transform, Option<&Charge>)>,
+) {
+    tree.fit(q.iter().map(|(_, _, t, _)| t.translation.truncate()));
 
     let mut qt = QuadTree::new(tree.bounds);
-    for (b, t) in &q {
-        qt.insert(t.translation.truncate(), b.mass);
+    // Regularized pairs go in as one composite at their centre of mass.
+    let mut composites: HashMap<Entity, (Vec2, f32, f32)> = HashMap::new();
+    for (e, b, t, c) in &q {
+        let charge = if settings.electrostatics {
+            c.map_or(0.0, |c| c.0)
+        } else {
+            0.0
+        };
+        let pos = t.translation.truncate();
+        if let Some(primary) = pairs.primary_of(e) {
+            let (moment, mass, q) = composites.entry(primary).or_default();
+            *moment += pos * b.mass;
+            *mass += b.mass;
+            *q += charge;
+            continue;
+        }
+        qt.insert(pos, b.mass, charge);
+    }
+    for pair in pairs.iter() {
+        if let Some(&(moment, mass, charge)) = composites.get(&pair.primary) {
+            qt.insert(moment / mass, mass, charge);
+        }
     }
     qt.build_mass_centers();
     tree.root = Some(qt);
@@ -684,8 +1300,10 @@ fn rebuild_quadtree(mut tree: ResMut<TreeState>, q: Query<(&Body, &Transform)>)
 
 fn apply_bh_forces(
     settings: Res<SimSettings>,
-    mut q: Query<(Entity, &mut Body, &Transform)>,
+    mut q: Query<(Entity, &mut Body, &Transform, Option<&Charge>)>,
     tree: Res<TreeState>,
+    force_law: Res<ActiveForceLaw>,
+    pairs: Res<RegularizedPairs>,
 ) {
     if tree.root.is_none() || !settings.running {
         return;
@@ -693,14 +1311,29 @@ fn apply_bh_forces(
     let qt = tree.root.as_ref().unwrap();
 
     // snapshot positions
-    let items: Vec<(Entity, Vec2)> = q
+    let items: Vec<(Entity, Vec2, f32, f32)> = q
         .iter()
-        .map(|(e, _, t)| (e, t.translation.truncate()))
+        .map(|(e, b, t, c)| (e, t.translation.truncate(), b.mass, c.map_or(0.0, |c| c.0)))
         .collect();
 
+    // Members of a regularized pair feel the field at the pair's centre of mass,
+    // where the tree holds their composite.
+    let mut composites: HashMap<Entity, (Vec2, f32)> = HashMap::new();
+    for &(e, pos, mass, _) in &items {
+        if let Some(primary) = pairs.primary_of(e) {
+            let (moment, total) = composites.entry(primary).or_default();
+            *moment += pos * mass;
+            *total += mass;
+        }
+    }
+
     // compute accelerations
     let mut acc_map: HashMap<Entity, Vec2> = HashMap::with_capacity(items.len());
-    for (e, pos) in items {
+    for (e, pos, mass, charge) in items {
+        let pos = match pairs.primary_of(e).and_then(|p| composites.get(&p)) {
+            Some(&(moment, total)) => moment / total,
+            None => pos,
+        };
         let density = qt.get_density_factor(pos);
 
         let theta = if settings.adaptive_theta {
@@ -710,46 +1343,95 @@ fn apply_bh_forces(
             settings.theta
         };
 
-        let softening = if settings.adaptive_softening {
-            // lerp(min, max, factor)
-            settings.softening_range.x
-                + density * (settings.softening_range.y - settings.softening_range.x)
-        } else {
-            settings.softening
-        };
+        let softening = settings.softening_at(density);
         let soft2 = softening * softening;
 
-        acc_map.insert(e, qt.approx_acc(pos, settings.g, theta, soft2));
+        let law = force_law.law.as_ref();
+        let mut acc = law.finish(qt.approx_acc(pos, settings.g, theta, soft2, law));
+        if settings.electrostatics && charge != 0.0 {
+            let field = qt.approx_field(pos, settings.coulomb_k, theta, soft2);
+            acc += field * charge / mass.max(1e-3);
+        }
+        acc_map.insert(e, acc);
     }
 
     // write back acc
-    for (e, mut b, _) in &mut q {
+    for (e, mut b, _, _) in &mut q {
         if let Some(&acc) = acc_map.get(&e) {
             b.acc = acc;
         }
     }
 }
 
-fn kick2(settings: Res<SimSettings>, mut q: Query<&mut Body>) {
+fn kick2(
+    settings: Res<SimSettings>,
+    pairs: Res<RegularizedPairs>,
+    mut q: Query<(Entity, &mut Body)>,
+) {
     if !settings.running {
         return;
     }
     let dt = settings.dt * settings.time_scale;
 
-    for mut b in &mut q {
+    for (e, mut b) in &mut q {
+        if pairs.contains(e) {
+            continue;
+        }
         // v = v_half + a * dt/2
         let v_full = b.vel + b.acc * dt * 0.5;
         b.vel = v_full.clamp_length_max(settings.max_vel);
     }
 }
 
-#[derive(Default)]
+/// Uniform grid of entities, shared by every neighbour search in the step. SPH,
+/// pair regularization and structure detection each refill it with their own
+/// cell size and bodies; `spatial_hash_build` refills it for collisions last.
+#[derive(Resource, Default)]
 struct SpatialHash {
     cell: f32,
     map: HashMap<(i32, i32), Vec<Entity>>,
 }
 
-fn spatial_hash_build(mut hash: Local<SpatialHash>, q: Query<(Entity, &Transform, &Body)>) {
+impl SpatialHash {
+    fn key(&self, p: Vec2) -> (i32, i32) {
+        (
+            (p.x / self.cell).floor() as i32,
+            (p.y / self.cell).floor() as i32,
+        )
+    }
+
+    fn reset(&mut self, cell: f32) {
+        self.cell = cell.max(1.0);
+        self.map.clear();
+    }
+
+    fn insert(&mut self, e: Entity, p: Vec2) {
+        let key = self.key(p);
+        self.map.entry(key).or_default().push(e);
+    }
+
+    /// Occupied cells in key order. `HashMap` iteration order changes from run to
+    /// run, and the order pairs are resolved in decides who absorbs whom.
+    fn cells(&self) -> Vec<((i32, i32), &Vec<Entity>)> {
+        let mut cells: Vec<_> = self.map.iter().map(|(k, v)| (*k, v)).collect();
+        cells.sort_unstable_by_key(|(k, _)| *k);
+        cells
+    }
+
+    /// Calls `f` for every entity in the cells overlapping the square of half-size `radius` around `p`.
+    fn for_each_near(&self, p: Vec2, radius: f32, mut f: impl FnMut(Entity)) {
+        let (lo, hi) = (self.key(p - radius), self.key(p + radius));
+        for x in lo.0..=hi.0 {
+            for y in lo.1..=hi.1 {
+                if let Some(v) = self.map.get(&(x, y)) {
+                    v.iter().copied().for_each(&mut f);
+                }
+            }
+        }
+    }
+}
+
+fn spatial_hash_build(mut hash: ResMut<SpatialHash>, q: Query<(Entity, &Transform, &Body)>) {
     let mut total_radius = 0.0;
     let mut count = 0;
     for (_, _, b) in &q {
@@ -757,21 +1439,16 @@ fn spatial_hash_build(mut hash: Local<SpatialHash>, q: Query<(Entity, &Transform
         count += 1;
     }
 
-    hash.cell = if count > 0 {
+    let cell = if count > 0 {
         let mean_radius = total_radius / count as f32;
-        (2.0 * mean_radius).max(1.0)
+        2.0 * mean_radius
     } else {
         24.0 // Default
     };
 
-    hash.map.clear();
+    hash.reset(cell);
     for (e, t, _b) in &q {
-        let p = t.translation.truncate();
-        let key = (
-            (p.x / hash.cell).floor() as i32,
-            (p.y / hash.cell).floor() as i32,
-        );
-        hash.map.entry(key).or_default().push(e);
+        hash.insert(e, t.translation.truncate());
     }
 }
 
@@ -781,12 +1458,22 @@ fn resolve_collisions(
     settings: Res<SimSettings>,
     mut stats: ResMut<SimStats>,
     mut q: ParamSet<(
-        Query<(Entity, &Body, &Transform, Option<&Player>)>, // read-only
-        Query<(Entity, &mut Body, &mut Transform)>,          // write-only
+        Query<
+            (
+                Entity,
+                &Body,
+                &Transform,
+                Option<&Player>,
+                Option<&Charge>,
+                Has<GasParticle>,
+            ),
+            Without<DiskParticle>,
+        >, // read-only; disk particles only fall in through accretion
+        Query<(Entity, &mut Body, &mut Transform, Option<&mut Charge>)>, // write-only
     )>,
     mut died: EventWriter<PlayerDied>,
     mut ev_absorbed: EventWriter<BodyAbsorbed>,
-    hash: Local<SpatialHash>,
+    hash: Res<SpatialHash>,
 ) {
     let neighbor_offsets = [
         (-1, -1),
@@ -809,6 +1496,7 @@ fn resolve_collisions(
                 loser: Entity,
                 new_mass: f32,
                 new_vel: Vec2,
+                loser_charge: f32,
                 player_died: bool,
             }
 
@@ -817,7 +1505,7 @@ fn resolve_collisions(
 
             {
                 let q_read = q.p0();
-                for (cell_key, ents) in hash.map.iter() {
+                for (cell_key, ents) in hash.cells() {
                     let mut candidates: Vec<Entity> = Vec::new();
                     for off in neighbor_offsets {
                         let key = (cell_key.0 + off.0, cell_key.1 + off.1);
@@ -830,7 +1518,7 @@ fn resolve_collisions(
                         if removed.contains(&a) {
                             continue;
                         }
-                        let Ok((ea, ba, ta, pla)) = q_read.get(a) else {
+                        let Ok((ea, ba, ta, pla, ca, gas_a)) = q_read.get(a) else {
                             continue;
                         };
                         let pa = ta.translation.truncate();
@@ -840,9 +1528,13 @@ fn resolve_collisions(
                             if a == b || removed.contains(&b) {
                                 continue;
                             }
-                            let Ok((eb, bb, tb, plb)) = q_read.get(b) else {
+                            let Ok((eb, bb, tb, plb, cb, gas_b)) = q_read.get(b) else {
                                 continue;
                             };
+                            // Gas–gas contact is handled by SPH pressure.
+                            if gas_a && gas_b {
+                                continue;
+                            }
                             let pb = tb.translation.truncate();
                             let rb = radius_of(bb);
 
@@ -853,7 +1545,10 @@ fn resolve_collisions(
 
                             let a_is_bh = ba.class == Class::BlackHole;
                             let b_is_bh = bb.class == Class::BlackHole;
-                            let a_wins = if a_is_bh && !b_is_bh {
+                            // Solid bodies always sweep up the gas they touch.
+                            let a_wins = if gas_a != gas_b {
+                                gas_b
+                            } else if a_is_bh && !b_is_bh {
                                 true
                             } else if b_is_bh && !a_is_bh {
                                 false
@@ -872,6 +1567,7 @@ fn resolve_collisions(
                                     loser: eb,
                                     new_mass,
                                     new_vel,
+                                    loser_charge: cb.map_or(0.0, |c| c.0),
                                     player_died: plb.is_some(),
                                 });
                                 ev_absorbed.send(BodyAbsorbed {
@@ -889,6 +1585,7 @@ fn resolve_collisions(
                                     loser: ea,
                                     new_mass,
                                     new_vel,
+                                    loser_charge: ca.map_or(0.0, |c| c.0),
                                     player_died: pla.is_some(),
                                 });
                                 ev_absorbed.send(BodyAbsorbed {
@@ -906,18 +1603,23 @@ fn resolve_collisions(
             }
 
             let mut already_gone: HashSet<Entity> = HashSet::new();
+            // Charge each winner swept up this tick, including whatever its
+            // losers had swept up before they were absorbed in turn.
+            let mut added_charge: HashMap<Entity, f32> = HashMap::new();
             let mut q_write = q.p1();
             for m in merges {
                 if already_gone.contains(&m.loser) {
                     continue;
                 }
-                if let Ok((_, mut bw, _)) = q_write.get_mut(m.winner) {
+                if let Ok((_, mut bw, _, _)) = q_write.get_mut(m.winner) {
                     bw.mass = m.new_mass;
                     bw.class = Class::from_mass(bw.mass);
                     bw.vel = m.new_vel;
                 } else {
                     continue;
                 }
+                let carried = added_charge.remove(&m.loser).unwrap_or(0.0);
+                *added_charge.entry(m.winner).or_default() += m.loser_charge + carried;
                 if m.player_died {
                     died.send(PlayerDied);
                 }
@@ -927,6 +1629,20 @@ fn resolve_collisions(
                     stats.0 = stats.0.saturating_sub(1);
                 }
             }
+            let mut added_charge: Vec<(Entity, f32)> = added_charge.into_iter().collect();
+            added_charge.sort_by_key(|(e, _)| *e);
+            for (winner, added) in added_charge {
+                if added == 0.0 {
+                    continue;
+                }
+                match q_write.get_mut(winner) {
+                    Ok((_, _, _, Some(mut c))) => c.0 += added,
+                    Ok(_) => {
+                        commands.entity(winner).insert(Charge(added));
+                    }
+                    Err(_) => {}
+                }
+            }
         }
         CollisionMode::Elastic => {
             struct ElasticResult {
@@ -939,7 +1655,7 @@ fn resolve_collisions(
 
             {
                 let q_read = q.p0();
-                for (cell_key, ents) in hash.map.iter() {
+                for (cell_key, ents) in hash.cells() {
                     let mut candidates: Vec<Entity> = Vec::new();
                     for off in neighbor_offsets {
                         let key = (cell_key.0 + off.0, cell_key.1 + off.1);
@@ -952,7 +1668,7 @@ fn resolve_collisions(
                         if processed.contains(&a) {
                             continue;
                         }
-                        let Ok((ea, ba, ta, _)) = q_read.get(a) else {
+                        let Ok((ea, ba, ta, _, _, gas_a)) = q_read.get(a) else {
                             continue;
                         };
                         let pa = ta.translation.truncate();
@@ -962,9 +1678,12 @@ fn resolve_collisions(
                             if a == b || processed.contains(&b) {
                                 continue;
                             }
-                            let Ok((eb, bb, tb, _)) = q_read.get(b) else {
+                            let Ok((eb, bb, tb, _, _, gas_b)) = q_read.get(b) else {
                                 continue;
                             };
+                            if gas_a || gas_b {
+                                continue;
+                            }
                             let pb = tb.translation.truncate();
                             let rb = radius_of(bb);
 
@@ -1019,7 +1738,7 @@ fn resolve_collisions(
 
             let mut q_write = q.p1();
             for update in updates {
-                if let Ok((_, mut body, mut trans)) = q_write.get_mut(update.entity) {
+                if let Ok((_, mut body, mut trans, _)) = q_write.get_mut(update.entity) {
                     body.vel = update.new_vel;
                     trans.translation.x = update.new_pos.x;
                     trans.translation.y = update.new_pos.y;
@@ -1030,11 +1749,17 @@ fn resolve_collisions(
 }
 
 fn update_render(
-    mut q: Query<(&Body, &mut Sprite, &mut SmoothSize)>,
+    mut q: Query<(
+        &Body,
+        &mut Sprite,
+        &mut SmoothSize,
+        Option<&Charge>,
+        Has<DiskParticle>,
+    )>,
     time: Res<Time>,
     settings: Res<SimSettings>,
 ) {
-    for (b, mut s, mut smooth_size) in &mut q {
+    for (b, mut s, mut smooth_size, charge, in_disk) in &mut q {
         smooth_size.target_radius = Class::radius_for_mass(b.mass);
 
         let current_size = s
@@ -1047,7 +1772,13 @@ fn update_render(
         s.custom_size = Some(Vec2::splat(new_size));
 
         let glow = b.class.glow();
-        let linear_rgba: LinearRgba = b.class.color(settings.color_palette).into();
+        let base = match charge {
+            Some(c) if settings.electrostatics && c.0 > 0.0 => Color::srgb(1.0, 0.35, 0.3),
+            Some(c) if settings.electrostatics && c.0 < 0.0 => Color::srgb(0.3, 0.55, 1.0),
+            _ if in_disk => Color::srgb(1.0, 0.55, 0.2),
+            _ => b.class.color(settings.color_palette),
+        };
+        let linear_rgba: LinearRgba = base.into();
         let new_color: Color = (linear_rgba * glow).into();
         s.color = new_color;
     }
@@ -1058,20 +1789,14 @@ fn spawn_bursts(
     mut commands: Commands,
     mut stats: ResMut<SimStats>,
     settings: Res<SimSettings>,
-    mut seeded_rng: Option<ResMut<SeededRng>>,
+    mut sim_rng: ResMut<SimRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng_source = sim_rng.stream(RngStream::Bursts);
     for e in ev.read() {
         if stats.0 >= settings.spawn_limit {
             continue;
         }
 
-        let mut rng_source: Box<dyn RngCore> = if let Some(seeded) = seeded_rng.as_mut() {
-            Box::new(&mut seeded.0)
-        } else {
-            Box::new(&mut rng)
-        };
-
         let count = e.count.min(settings.spawn_limit - stats.0);
         for _ in 0..count {
             let r = rng_source.gen::<f32>() * e.radius;
@@ -1085,7 +1810,7 @@ fn spawn_bursts(
             );
             let mass = e.base_mass * rng_source.gen_range(0.5..1.5);
             let class = Class::from_mass(mass);
-            commands.spawn((
+            let mut entity = commands.spawn((
                 Body {
                     mass,
                     vel: tangential + jitter,
@@ -1105,30 +1830,47 @@ fn spawn_bursts(
                     ..default()
                 },
             ));
+            if settings.electrostatics {
+                let sign = if rng_source.gen::<bool>() { 1.0 } else { -1.0 };
+                entity.insert(Charge(sign * settings.burst_charge));
+            }
         }
         stats.0 += count;
     }
 }
 
-fn handle_reset(
-    mut commands: Commands,
-    mut ev_reset: EventReader<ResetEvent>,
-    body_q: Query<Entity, With<Body>>,
-    mut stats: ResMut<SimStats>,
-    mut settings: ResMut<SimSettings>,
+/// Clears the world and respawns the current scenario from `seed`. Settings
+/// go back to the scenario's, except for the deterministic switch and a custom
+/// force law.
+fn reset_world(
+    commands: &mut Commands,
+    doomed: impl IntoIterator<Item = Entity>,
+    stats: &mut SimStats,
+    settings: &mut SimSettings,
+    seed: u64,
 ) {
-    if ev_reset.is_empty() {
-        return;
-    }
-    ev_reset.clear();
-
-    for e in &body_q {
+    for e in doomed {
         commands.entity(e).despawn_recursive();
     }
     stats.0 = 0;
 
+    let force_law = settings.force_law;
+    let deterministic = settings.deterministic;
     *settings = SimSettings::from_scenario(settings.scenario);
-    spawn_initial_bodies_inner(&mut commands, stats.as_mut(), &*settings);
+    settings.force_law = force_law;
+    settings.deterministic = deterministic;
+    settings.seed = seed;
+    spawn_initial_bodies_inner(commands, stats, settings);
+}
+
+/// Seed for the next run: the current one while deterministic, so a pinned run
+/// can be replayed, otherwise a fresh one.
+fn next_seed(settings: &SimSettings) -> u64 {
+    if settings.deterministic {
+        settings.seed
+    } else {
+        rng::entropy_seed()
+    }
 }
 
 fn spawn_trails(
@@ -1136,7 +1878,7 @@ fn spawn_trails(
     time: Res<Time>,
     mut timer: ResMut<TrailSpawnTimer>,
     settings: Res<SimSettings>,
-    body_q: Query<(&Transform, &Body)>,
+    body_q: Query<(&Transform, &Body), Without<GasParticle>>,
 ) {
     timer.0.tick(time.delta());
     if !settings.trails_enabled || !timer.0.just_finished() {
@@ -1216,13 +1958,18 @@ fn update_score(
 
 fn spawn_hazards(
     mut commands: Commands,
-    time: Res<Time>,
     mut timer: ResMut<HazardSpawnTimer>,
     mut ev_spawn: EventWriter<SpawnBurst>,
     settings: Res<SimSettings>,
+    mut sim_rng: ResMut<SimRng>,
     q_player: Query<&Transform, With<Player>>,
 ) {
-    timer.0.tick(time.delta());
+    // Sim time, not wall time, so hazards arrive on the same tick in every run.
+    if !settings.running {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    timer.0.tick(std::time::Duration::from_secs_f32(dt));
     if !timer.0.just_finished() {
         return;
     }
@@ -1233,7 +1980,7 @@ fn spawn_hazards(
         Vec2::ZERO
     };
 
-    let mut rng = rand::thread_rng();
+    let rng = sim_rng.stream(RngStream::Hazards);
     let hazard_type = rng.gen_range(0..3);
 
     match hazard_type {
@@ -1311,14 +2058,21 @@ fn spawn_hazards(
     }
 }
 
-fn update_mission(mut mission: ResMut<Mission>, time: Res<Time>) {
-    if mission.completed {
+fn advance_clock(settings: Res<SimSettings>, mut clock: ResMut<SimClock>) {
+    clock.tick += 1;
+    if settings.running {
+        clock.elapsed += settings.dt * settings.time_scale;
+    }
+}
+
+fn update_mission(mut mission: ResMut<Mission>, settings: Res<SimSettings>) {
+    if mission.completed || !settings.running {
         return;
     }
 
     match mission.objective {
         Objective::Survive => {
-            mission.progress += time.delta_seconds();
+            mission.progress += settings.dt * settings.time_scale;
             if mission.progress >= mission.goal {
                 mission.completed = true;
             }
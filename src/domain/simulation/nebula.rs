@@ -0,0 +1,251 @@
+//! Nebulae: procedurally placed gas regions that drag on bodies and feed small ones.
+//!
+//! Each [`Nebula`] is an entity with a Gaussian density profile roughened by
+//! value noise. Bodies moving relative to the gas feel quadratic drag scaled by
+//! area over mass, so debris settles into the gas flow (a spinning nebula damps
+//! it into a disk), and bodies below `NebulaSpec::accretion_max_mass` sweep up mass.
+//! Nebulae render as soft translucent clouds behind everything else.
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use super::sph::GasParticle;
+use super::{Body, Class, SimSettings};
+
+/// Scenario-level description of the nebulae to scatter on spawn/reset.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NebulaSpec {
+    pub count: usize,
+    pub radius_range: (f32, f32),
+    /// Nebula centres are placed uniformly within this distance of the origin.
+    pub spread: f32,
+    pub peak_density: f32,
+    /// Quadratic drag coefficient (acceleration = C·ρ·|v|·v·r²/m).
+    pub drag_coefficient: f32,
+    /// Fraction of swept-up gas that sticks to a body.
+    pub accretion_efficiency: f32,
+    /// Bodies heavier than this only feel drag.
+    pub accretion_max_mass: f32,
+    /// Angular speed of the gas around each nebula centre (rad/s).
+    pub spin: f32,
+}
+
+impl Default for NebulaSpec {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            radius_range: (300.0, 700.0),
+            spread: 1800.0,
+            peak_density: 1.0,
+            drag_coefficient: 0.01,
+            accretion_efficiency: 0.002,
+            accretion_max_mass: 500.0,
+            spin: 0.0,
+        }
+    }
+}
+
+/// A gas region. Density peaks at `centre` and is negligible beyond 1.5 × `radius`.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Nebula {
+    pub centre: Vec2,
+    pub radius: f32,
+    pub density: f32,
+    pub spin: f32,
+    pub noise_seed: u32,
+}
+
+impl Nebula {
+    pub fn density_at(&self, p: Vec2) -> f32 {
+        let d2 = (p - self.centre).length_squared();
+        let cutoff = 1.5 * self.radius;
+        if d2 > cutoff * cutoff {
+            return 0.0;
+        }
+        let sigma = 0.5 * self.radius;
+        let profile = (-d2 / (2.0 * sigma * sigma)).exp();
+        let n = value_noise(p / (0.35 * self.radius), self.noise_seed);
+        self.density * profile * (0.5 + n)
+    }
+
+    /// Velocity of the gas at `p` (rigid rotation about the centre).
+    pub fn gas_velocity(&self, p: Vec2) -> Vec2 {
+        (p - self.centre).perp() * self.spin
+    }
+}
+
+fn hash2(x: i32, y: i32, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ seed.wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h & 0x00ff_ffff) as f32 / 0x00ff_ffff as f32
+}
+
+/// Smooth 2D value noise in [0, 1].
+fn value_noise(p: Vec2, seed: u32) -> f32 {
+    let (x0, y0) = (p.x.floor(), p.y.floor());
+    let (fx, fy) = (p.x - x0, p.y - y0);
+    let (sx, sy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let (ix, iy) = (x0 as i32, y0 as i32);
+    let a = hash2(ix, iy, seed);
+    let b = hash2(ix + 1, iy, seed);
+    let c = hash2(ix, iy + 1, seed);
+    let d = hash2(ix + 1, iy + 1, seed);
+    let top = a + (b - a) * sx;
+    let bottom = c + (d - c) * sx;
+    top + (bottom - top) * sy
+}
+
+/// Shared radial-falloff texture for nebula sprites.
+#[derive(Resource)]
+pub(super) struct NebulaTexture(pub(super) Handle<Image>);
+
+pub(super) fn init_nebula_texture(mut commands: Commands, images: Option<ResMut<Assets<Image>>>) {
+    let Some(mut images) = images else {
+        return;
+    };
+    const SIZE: u32 = 128;
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let p = (Vec2::new(x as f32, y as f32) + 0.5) / SIZE as f32 * 2.0 - 1.0;
+            let falloff = (1.0 - p.length()).clamp(0.0, 1.0);
+            let wisps = 0.6 + 0.4 * value_noise(p * 4.0, 7);
+            let alpha = (falloff * falloff * wisps * 255.0) as u8;
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+    let image = Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    commands.insert_resource(NebulaTexture(images.add(image)));
+}
+
+/// Scatters `spec.count` nebulae (called from the initial spawn) and returns them.
+pub(super) fn spawn_nebulae<R: RngCore + ?Sized>(
+    commands: &mut Commands,
+    spec: &NebulaSpec,
+    rng: &mut R,
+) -> Vec<Nebula> {
+    let mut clouds = Vec::with_capacity(spec.count);
+    for _ in 0..spec.count {
+        let centre =
+            Vec2::from_angle(rng.gen::<f32>() * TAU) * rng.gen::<f32>().sqrt() * spec.spread;
+        let (lo, hi) = spec.radius_range;
+        let radius = if hi > lo { rng.gen_range(lo..hi) } else { lo };
+        let tint = Color::srgba(
+            rng.gen_range(0.35..0.7),
+            rng.gen_range(0.25..0.5),
+            rng.gen_range(0.6..1.0),
+            0.35,
+        );
+        let nebula = Nebula {
+            centre,
+            radius,
+            density: spec.peak_density,
+            spin: spec.spin,
+            noise_seed: rng.gen(),
+        };
+        clouds.push(nebula);
+        spawn_nebula(commands, nebula, tint);
+    }
+    clouds
+}
+
+/// Spawns one nebula entity with its cloud sprite drawn behind the bodies.
+pub(super) fn spawn_nebula(commands: &mut Commands, nebula: Nebula, tint: Color) -> Entity {
+    commands
+        .spawn((
+            nebula,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: tint,
+                    custom_size: Some(Vec2::splat(nebula.radius * 3.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(nebula.centre.extend(-10.0)),
+                ..default()
+            },
+        ))
+        .id()
+}
+
+/// Gives freshly spawned nebulae the shared cloud texture.
+pub(super) fn style_new_nebulae(
+    texture: Option<Res<NebulaTexture>>,
+    mut q: Query<&mut Handle<Image>, Added<Nebula>>,
+) {
+    let Some(texture) = texture else {
+        return;
+    };
+    for mut image in &mut q {
+        *image = texture.0.clone();
+    }
+}
+
+/// Drag towards the local gas velocity, plus accretion for small bodies.
+/// Accreted gas brings its momentum along. SPH gas particles are part of the
+/// cloud themselves and are left alone.
+pub(super) fn apply_nebula_drag(
+    settings: Res<SimSettings>,
+    nebulae: Query<&Nebula>,
+    mut q: Query<(&mut Body, &Transform), Without<GasParticle>>,
+) {
+    if !settings.running || nebulae.is_empty() {
+        return;
+    }
+    let spec = &settings.nebulae;
+    let dt = settings.dt * settings.time_scale;
+    let clouds: Vec<Nebula> = nebulae.iter().copied().collect();
+
+    for (mut b, t) in &mut q {
+        let pos = t.translation.truncate();
+        let radius = Class::radius_for_mass(b.mass);
+        let area = radius * radius;
+        let mut acc = Vec2::ZERO;
+        let mut swept = 0.0;
+        let mut swept_momentum = Vec2::ZERO;
+        for n in &clouds {
+            let rho = n.density_at(pos);
+            if rho <= 0.0 {
+                continue;
+            }
+            let gas_vel = n.gas_velocity(pos);
+            let v_rel = b.vel - gas_vel;
+            let speed = v_rel.length();
+            let mut drag = -spec.drag_coefficient * rho * speed * v_rel * area / b.mass.max(1e-3);
+            // Never let drag reverse the relative velocity within one step.
+            if dt > 0.0 {
+                drag = drag.clamp_length_max(speed / dt);
+            }
+            acc += drag;
+            let mass = rho * area * speed * dt;
+            swept += mass;
+            swept_momentum += mass * gas_vel;
+        }
+        b.acc += acc;
+        if swept > 0.0 && b.mass < spec.accretion_max_mass {
+            let dm = spec.accretion_efficiency * swept;
+            let gained = spec.accretion_efficiency * swept_momentum;
+            b.vel = (b.vel * b.mass + gained) / (b.mass + dm);
+            b.mass += dm;
+            b.class = Class::from_mass(b.mass);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Orbital mechanics helpers: dominant-attractor search and osculating elements.
+//!
+//! Every frame `update_orbits` pairs each body with the attractor whose Hill
+//! sphere it sits in and stores the resulting two-body elements in the
+//! [`Orbits`] resource, so the HUD, missions and AI can ask "what does this
+//! orbit, and is it bound?" without touching simulation internals.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use super::sph::GasParticle;
+use super::{Body, Class, Player, SimSettings};
+
+/// Bodies below this mass are never considered as attractors (unless they are the player).
+pub const ATTRACTOR_MIN_MASS: f32 = 500.0;
+
+/// Coarse classification of a two-body orbit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OrbitKind {
+    /// Negative specific energy: the body will keep circling its attractor.
+    #[default]
+    Bound,
+    /// Zero or positive specific energy: the body leaves on the next pass.
+    Escaping,
+    /// Bound now, but it arrived on an escaping trajectory (or from another attractor).
+    Captured,
+}
+
+impl OrbitKind {
+    pub fn is_bound(&self) -> bool {
+        !matches!(self, OrbitKind::Escaping)
+    }
+}
+
+/// Osculating (instantaneous) two-body elements in the attractor's frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrbitalElements {
+    /// Negative on hyperbolic orbits, infinite on parabolic ones.
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    /// Angle of periapsis from the +x axis, counter-clockwise, in radians.
+    pub argument_of_periapsis: f32,
+    /// Orbital period in sim seconds; `None` when unbound.
+    pub period: Option<f32>,
+    pub periapsis: f32,
+    /// Furthest distance from the attractor; `None` when unbound.
+    pub apoapsis: Option<f32>,
+    /// Specific orbital energy (v²/2 − μ/r).
+    pub specific_energy: f32,
+}
+
+impl OrbitalElements {
+    /// Computes elements from a relative state vector and gravitational parameter `mu = G·(M + m)`.
+    pub fn from_state(rel_pos: Vec2, rel_vel: Vec2, mu: f32) -> Self {
+        let r = rel_pos.length();
+        if r == 0.0 || mu <= 0.0 {
+            return Self::default();
+        }
+        let v2 = rel_vel.length_squared();
+        let h = rel_pos.perp_dot(rel_vel);
+        let energy = 0.5 * v2 - mu / r;
+        let e_vec = ((v2 - mu / r) * rel_pos - rel_pos.dot(rel_vel) * rel_vel) / mu;
+        let e = e_vec.length();
+
+        let semi_major_axis = if energy.abs() > f32::EPSILON {
+            -mu / (2.0 * energy)
+        } else {
+            f32::INFINITY
+        };
+        let periapsis = h * h / (mu * (1.0 + e));
+        let bound = energy < 0.0 && e < 1.0;
+        let (period, apoapsis) = if bound {
+            (
+                Some(TAU * (semi_major_axis.powi(3) / mu).sqrt()),
+                Some(semi_major_axis * (1.0 + e)),
+            )
+        } else {
+            (None, None)
+        };
+
+        Self {
+            semi_major_axis,
+            eccentricity: e,
+            argument_of_periapsis: e_vec.y.atan2(e_vec.x),
+            period,
+            periapsis,
+            apoapsis,
+            specific_energy: energy,
+        }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.specific_energy < 0.0 && self.eccentricity < 1.0
+    }
+}
+
+/// A body's current orbit around its dominant attractor.
+#[derive(Clone, Copy, Debug)]
+pub struct Orbit {
+    pub attractor: Entity,
+    pub elements: OrbitalElements,
+    pub kind: OrbitKind,
+}
+
+/// Latest orbit for every body that has an attractor.
+#[derive(Resource, Default)]
+pub struct Orbits {
+    map: HashMap<Entity, Orbit>,
+}
+
+impl Orbits {
+    pub fn get(&self, e: Entity) -> Option<&Orbit> {
+        self.map.get(&e)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &Orbit)> {
+        self.map.iter().map(|(e, o)| (*e, o))
+    }
+    /// Bodies currently orbiting `attractor` on a bound or captured orbit.
+    pub fn satellites_of(&self, attractor: Entity) -> impl Iterator<Item = (Entity, &Orbit)> {
+        self.iter()
+            .filter(move |(_, o)| o.attractor == attractor && o.kind.is_bound())
+    }
+}
+
+/// Radius of the Hill sphere of `mass` orbiting `primary_mass` at `distance`.
+pub fn hill_radius(mass: f32, primary_mass: f32, distance: f32) -> f32 {
+    if primary_mass <= 0.0 {
+        return f32::INFINITY;
+    }
+    distance * (mass / (3.0 * primary_mass)).cbrt()
+}
+
+#[derive(Clone, Copy)]
+struct Candidate {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    mass: f32,
+    /// Index into the candidate list of this candidate's own primary.
+    parent: Option<usize>,
+    hill: f32,
+}
+
+/// Picks the attractor for a body at `pos` with `mass`, skipping `skip`.
+///
+/// The strongest pull (g·M/d²) among heavier candidates wins, then we climb the
+/// hierarchy while the body lies outside that attractor's Hill sphere.
+fn dominant_attractor(
+    candidates: &[Candidate],
+    skip: Option<usize>,
+    pos: Vec2,
+    mass: f32,
+) -> Option<usize> {
+    let mut best: Option<(usize, f32)> = None;
+    for (i, c) in candidates.iter().enumerate() {
+        if Some(i) == skip || c.mass <= mass {
+            continue;
+        }
+        let d2 = (c.pos - pos).length_squared().max(1e-6);
+        let pull = c.mass / d2;
+        if best.is_none_or(|(_, p)| pull > p) {
+            best = Some((i, pull));
+        }
+    }
+    let mut idx = best?.0;
+    while let Some(parent) = candidates[idx].parent {
+        if (candidates[idx].pos - pos).length() <= candidates[idx].hill {
+            break;
+        }
+        idx = parent;
+    }
+    Some(idx)
+}
+
+pub(super) fn update_orbits(
+    settings: Res<SimSettings>,
+    mut orbits: ResMut<Orbits>,
+    q: Query<(Entity, &Body, &Transform, Option<&Player>), Without<GasParticle>>,
+) {
+    // Attractor candidates, heaviest first so every primary is resolved before its satellites.
+    let mut candidates: Vec<Candidate> = q
+        .iter()
+        .filter(|(_, b, _, p)| b.mass >= ATTRACTOR_MIN_MASS || p.is_some())
+        .map(|(e, b, t, _)| Candidate {
+            entity: e,
+            pos: t.translation.truncate(),
+            vel: b.vel,
+            mass: b.mass,
+            parent: None,
+            hill: f32::INFINITY,
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.mass.total_cmp(&a.mass));
+
+    for i in 0..candidates.len() {
+        let c = candidates[i];
+        if let Some(p) = dominant_attractor(&candidates[..i], None, c.pos, c.mass) {
+            let parent = candidates[p];
+            candidates[i].parent = Some(p);
+            candidates[i].hill = hill_radius(c.mass, parent.mass, (c.pos - parent.pos).length());
+        }
+    }
+    let index_of: HashMap<Entity, usize> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.entity, i))
+        .collect();
+
+    let mut next: HashMap<Entity, Orbit> = HashMap::with_capacity(orbits.map.len());
+    for (e, b, t, _) in &q {
+        let pos = t.translation.truncate();
+        let skip = index_of.get(&e).copied();
+        let Some(ai) = dominant_attractor(&candidates, skip, pos, b.mass) else {
+            continue;
+        };
+        let a = candidates[ai];
+        let mu = settings.g * (a.mass + b.mass);
+        let elements = OrbitalElements::from_state(pos - a.pos, b.vel - a.vel, mu);
+
+        let prev = orbits.map.get(&e);
+        let kind = if !elements.is_bound() {
+            OrbitKind::Escaping
+        } else {
+            match prev {
+                Some(p) if p.attractor == a.entity => match p.kind {
+                    OrbitKind::Escaping | OrbitKind::Captured => OrbitKind::Captured,
+                    OrbitKind::Bound => OrbitKind::Bound,
+                },
+                // Switched attractor while bound: it was handed over, i.e. captured.
+                Some(_) => OrbitKind::Captured,
+                None => OrbitKind::Bound,
+            }
+        };
+
+        next.insert(
+            e,
+            Orbit {
+                attractor: a.entity,
+                elements,
+                kind,
+            },
+        );
+    }
+    orbits.map = next;
+}
+
+/// Short human-readable summary for HUD labels, e.g. "Bound around Star".
+pub fn describe(orbit: &Orbit, attractor_class: Option<Class>) -> String {
+    let around = attractor_class
+        .map(|c| format!("{:?}", c))
+        .unwrap_or_else(|| "?".into());
+    format!("{:?} around {}", orbit.kind, around)
+}
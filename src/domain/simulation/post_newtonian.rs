@@ -0,0 +1,256 @@
+//! Post-Newtonian corrections for close black-hole pairs.
+//!
+//! For every pair of `Class::BlackHole` bodies closer than
+//! [`PostNewtonianSettings::pair_threshold`] the relative acceleration gains the
+//! harmonic-gauge 1PN term (periastron precession) and the 2.5PN
+//! radiation-reaction term, which drains orbital energy so binaries inspiral.
+//! The energy carried off is booked per pair; when the two horizons touch they
+//! merge into one remnant, a [`BlackHoleMerger`] event is sent and, optionally,
+//! a ripple kicks nearby bodies outward.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{Body, BodyId, Class, Player, SimSettings, SimStats};
+
+/// Tuning for [`apply_post_newtonian`]. `c` is the speed of light in sim units;
+/// smaller values make relativistic effects stronger.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostNewtonianSettings {
+    pub enabled: bool,
+    pub c: f32,
+    /// Only pairs closer than this get PN terms.
+    pub pair_threshold: f32,
+    /// 1PN conservative terms.
+    pub precession: bool,
+    /// 2.5PN dissipative terms.
+    pub radiation_reaction: bool,
+    /// Peak outward kick from a merger, in units of the fraction of rest mass
+    /// radiated times c. Zero disables the ripple.
+    pub ripple_strength: f32,
+    pub ripple_radius: f32,
+}
+
+impl Default for PostNewtonianSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            c: 8000.0,
+            pair_threshold: 800.0,
+            precession: true,
+            radiation_reaction: true,
+            ripple_strength: 0.0,
+            ripple_radius: 1500.0,
+        }
+    }
+}
+
+/// Sent when two black holes coalesce under the PN terms.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BlackHoleMerger {
+    pub remnant: Entity,
+    pub position: Vec2,
+    pub remnant_mass: f32,
+    /// Inspiral losses booked while the pair was tracked, plus the final burst.
+    pub energy_radiated: f32,
+}
+
+/// Fraction of the total rest mass an equal-mass, non-spinning merger radiates,
+/// scaled by (4η)² for unequal masses.
+const MERGER_EFFICIENCY: f32 = 0.05;
+
+/// Factor on `g` that launches a pair of the given masses on a circular orbit of
+/// separation `r` once the 1PN terms act, where ω² = (GM/r³)·[1 − (3 − η)·GM/(r c²)];
+/// one unless both are black holes under precession.
+pub fn circular_orbit_factor(
+    g: f32,
+    m1: f32,
+    m2: f32,
+    r: f32,
+    settings: &PostNewtonianSettings,
+) -> f32 {
+    let holes =
+        Class::from_mass(m1) == Class::BlackHole && Class::from_mass(m2) == Class::BlackHole;
+    if !settings.enabled || !settings.precession || !holes || r <= 0.0 {
+        return 1.0;
+    }
+    let total = m1 + m2;
+    let eta = m1 * m2 / (total * total);
+    (1.0 - (3.0 - eta) * g * total / (r * settings.c * settings.c)).max(0.0)
+}
+
+/// 1PN part of the relative acceleration a₁ − a₂ for separation `x = x₁ − x₂`
+/// and relative velocity `v = v₁ − v₂`.
+fn precession_term(gm: f32, eta: f32, x: Vec2, v: Vec2, c: f32) -> Vec2 {
+    let r = x.length();
+    let n = x / r;
+    let rdot = n.dot(v);
+    let v2 = v.length_squared();
+    let radial = (4.0 + 2.0 * eta) * gm / r - (1.0 + 3.0 * eta) * v2 + 1.5 * eta * rdot * rdot;
+    gm / (c * c * r * r) * (radial * n + (4.0 - 2.0 * eta) * rdot * v)
+}
+
+/// 2.5PN radiation-reaction part of the relative acceleration (same conventions).
+fn reaction_term(gm: f32, eta: f32, x: Vec2, v: Vec2, c: f32) -> Vec2 {
+    let r = x.length();
+    let n = x / r;
+    let rdot = n.dot(v);
+    let v2 = v.length_squared();
+    let k = 1.6 * eta * gm * gm / (c.powi(5) * r * r * r);
+    k * ((3.0 * v2 + 17.0 / 3.0 * gm / r) * rdot * n - (v2 + 3.0 * gm / r) * v)
+}
+
+#[derive(Clone, Copy)]
+struct Hole {
+    entity: Entity,
+    id: BodyId,
+    pos: Vec2,
+    vel: Vec2,
+    /// `vel` advanced to the end of the step, as the second kick will leave it;
+    /// the velocity-dependent terms need it there rather than at the half step.
+    vel_end: Vec2,
+    mass: f32,
+    player: bool,
+    /// Absorbed a partner this step; its body must be rewritten.
+    remnant: bool,
+}
+
+pub(super) fn apply_post_newtonian(
+    mut commands: Commands,
+    settings: Res<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    // Keyed by id rather than entity so the books survive a snapshot restore.
+    mut radiated: Local<HashMap<(BodyId, BodyId), f32>>,
+    mut mergers: EventWriter<BlackHoleMerger>,
+    mut q: Query<(
+        Entity,
+        Option<&BodyId>,
+        &mut Body,
+        &mut Transform,
+        Has<Player>,
+    )>,
+) {
+    let pn = settings.post_newtonian;
+    if !settings.running || !pn.enabled {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    // Holes spawned this tick have no id yet and join in on the next one.
+    let mut holes: Vec<Hole> = q
+        .iter()
+        .filter(|(_, _, b, _, _)| b.class == Class::BlackHole)
+        .filter_map(|(e, id, b, t, player)| {
+            Some(Hole {
+                entity: e,
+                id: *id?,
+                pos: t.translation.truncate(),
+                vel: b.vel,
+                vel_end: b.vel + b.acc * dt * 0.5,
+                mass: b.mass,
+                player,
+                remnant: false,
+            })
+        })
+        .collect();
+    holes.sort_by_key(|h| h.id);
+    radiated
+        .retain(|(a, b), _| holes.iter().any(|h| h.id == *a) && holes.iter().any(|h| h.id == *b));
+
+    let mut acc: HashMap<Entity, Vec2> = HashMap::new();
+    let mut merged: Vec<bool> = vec![false; holes.len()];
+    let mut ripples: Vec<(Vec2, f32, [Entity; 2])> = Vec::new();
+    for i in 0..holes.len() {
+        for j in (i + 1)..holes.len() {
+            if merged[i] || merged[j] {
+                continue;
+            }
+            let (a, b) = (holes[i], holes[j]);
+            let x = a.pos - b.pos;
+            let r = x.length();
+            if r >= pn.pair_threshold || r <= 0.0 {
+                continue;
+            }
+            let total = a.mass + b.mass;
+            let eta = a.mass * b.mass / (total * total);
+            let gm = settings.g * total;
+            let key = (a.id, b.id);
+
+            if r <= Class::radius_for_mass(a.mass) + Class::radius_for_mass(b.mass) {
+                // Coalescence: momentum-conserving remnant at the centre of mass,
+                // lighter by the energy of the final burst.
+                let burst = MERGER_EFFICIENCY * (4.0 * eta).powi(2) * total * pn.c * pn.c;
+                let remnant_mass = total - burst / (pn.c * pn.c);
+                let (keep, lose) = if a.player || (!b.player && a.mass >= b.mass) {
+                    (i, j)
+                } else {
+                    (j, i)
+                };
+                let position = (a.pos * a.mass + b.pos * b.mass) / total;
+                let velocity = (a.vel * a.mass + b.vel * b.mass) / total;
+                holes[keep].pos = position;
+                holes[keep].vel = velocity;
+                holes[keep].mass = remnant_mass;
+                holes[keep].remnant = true;
+                merged[lose] = true;
+                let energy = radiated.remove(&key).unwrap_or(0.0) + burst;
+                mergers.send(BlackHoleMerger {
+                    remnant: holes[keep].entity,
+                    position,
+                    remnant_mass,
+                    energy_radiated: energy,
+                });
+                if pn.ripple_strength > 0.0 {
+                    let kick = pn.ripple_strength * burst / (total * pn.c);
+                    ripples.push((position, kick, [a.entity, b.entity]));
+                }
+                continue;
+            }
+
+            let v = a.vel_end - b.vel_end;
+            let mut rel = Vec2::ZERO;
+            if pn.precession {
+                rel += precession_term(gm, eta, x, v, pn.c);
+            }
+            if pn.radiation_reaction {
+                let reaction = reaction_term(gm, eta, x, v, pn.c);
+                rel += reaction;
+                // Power drained from the orbit: −μ a_RR · v.
+                let mu = a.mass * b.mass / total;
+                *radiated.entry(key).or_default() += (-mu * reaction.dot(v)).max(0.0) * dt;
+            }
+            *acc.entry(a.entity).or_default() += rel * (b.mass / total);
+            *acc.entry(b.entity).or_default() -= rel * (a.mass / total);
+        }
+    }
+
+    for (idx, hole) in holes.iter().enumerate() {
+        if merged[idx] {
+            commands.entity(hole.entity).despawn_recursive();
+            stats.0 = stats.0.saturating_sub(1);
+        }
+    }
+    for (e, _, mut b, mut t, _) in &mut q {
+        if let Some(h) = holes.iter().find(|h| h.entity == e && h.remnant) {
+            b.mass = h.mass;
+            b.vel = h.vel;
+            b.class = Class::from_mass(h.mass);
+            t.translation.x = h.pos.x;
+            t.translation.y = h.pos.y;
+        }
+        if let Some(a) = acc.get(&e) {
+            b.acc += *a;
+        }
+        for &(centre, kick, pair) in &ripples {
+            if pair.contains(&e) {
+                continue;
+            }
+            let d = t.translation.truncate() - centre;
+            let dist = d.length();
+            if dist > 0.0 && dist < pn.ripple_radius {
+                b.vel += d / dist * kick * (1.0 - dist / pn.ripple_radius);
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use super::force_law::ForceLaw;
+
 #[derive(Clone, Copy)]
 pub struct Quad {
     pub center: Vec2,
@@ -35,11 +37,15 @@ pub enum Node {
         quad: Quad,
         pos: Vec2,
         mass: f32,
+        charge: f32,
     },
     Internal {
         quad: Quad,
         mass: f32,
         com: Vec2,
+        /// Net charge and its |q|-weighted centre.
+        charge: f32,
+        charge_centre: Vec2,
         children: [Box<Node>; 4],
     },
 }
@@ -54,64 +60,102 @@ impl QuadTree {
             root: Box::new(Node::Empty(bounds)),
         }
     }
-    pub fn insert(&mut self, p: Vec2, mass: f32) {
-        Self::insert_node(&mut self.root, p, mass);
+    pub fn insert(&mut self, p: Vec2, mass: f32, charge: f32) {
+        Self::insert_node(&mut self.root, p, mass, charge);
     }
 
-    fn insert_node(node: &mut Box<Node>, p: Vec2, mass: f32) {
+    fn insert_node(node: &mut Box<Node>, p: Vec2, mass: f32, charge: f32) {
         match node.as_mut() {
             Node::Empty(q) => {
                 if !q.contains(p) {
                     return;
                 }
-                *node = Box::new(Node::Leaf {
+                **node = Node::Leaf {
                     quad: *q,
                     pos: p,
                     mass,
-                });
+                    charge,
+                };
             }
-            Node::Leaf { quad, pos, mass: m } => {
+            Node::Leaf {
+                quad,
+                pos,
+                mass: m,
+                charge: c,
+            } => {
                 let quads = quad.subdivide();
                 let mut children: [Box<Node>; 4] = quads.map(|q| Box::new(Node::Empty(q)));
-                Self::insert_node(&mut children[Self::child_index(*pos, *quad)], *pos, *m);
-                Self::insert_node(&mut children[Self::child_index(p, *quad)], p, mass);
-                *node = Box::new(Node::Internal {
+                Self::insert_node(&mut children[Self::child_index(*pos, *quad)], *pos, *m, *c);
+                Self::insert_node(&mut children[Self::child_index(p, *quad)], p, mass, charge);
+                **node = Node::Internal {
                     quad: *quad,
                     mass: 0.0,
                     com: Vec2::ZERO,
+                    charge: 0.0,
+                    charge_centre: Vec2::ZERO,
                     children,
-                });
+                };
             }
             Node::Internal { quad, children, .. } => {
                 let idx = Self::child_index(p, *quad);
-                Self::insert_node(&mut children[idx], p, mass);
+                Self::insert_node(&mut children[idx], p, mass, charge);
             }
         }
     }
 
+    /// Index into `Quad::subdivide`'s NW, NE, SW, SE order.
     fn child_index(p: Vec2, quad: Quad) -> usize {
         let right = (p.x > quad.center.x) as usize;
-        let top = (p.y > quad.center.y) as usize;
-        (top << 1) | right
+        let bottom = (p.y <= quad.center.y) as usize;
+        (bottom << 1) | right
     }
 
     pub fn build_mass_centers(&mut self) {
-        fn compute(node: &mut Node) -> (f32, Vec2) {
+        struct Moments {
+            mass: f32,
+            com: Vec2,
+            charge: f32,
+            abs_charge: f32,
+            charge_centre: Vec2,
+        }
+        fn compute(node: &mut Node) -> Moments {
             match node {
-                Node::Empty(_) => (0.0, Vec2::ZERO),
-                Node::Leaf { mass, pos, .. } => (*mass, *pos),
+                Node::Empty(_) => Moments {
+                    mass: 0.0,
+                    com: Vec2::ZERO,
+                    charge: 0.0,
+                    abs_charge: 0.0,
+                    charge_centre: Vec2::ZERO,
+                },
+                Node::Leaf {
+                    mass, pos, charge, ..
+                } => Moments {
+                    mass: *mass,
+                    com: *pos,
+                    charge: *charge,
+                    abs_charge: charge.abs(),
+                    charge_centre: *pos,
+                },
                 Node::Internal {
                     children,
                     mass,
                     com,
+                    charge,
+                    charge_centre,
                     ..
                 } => {
                     let mut total_m = 0.0;
                     let mut weighted = Vec2::ZERO;
+                    let mut total_q = 0.0;
+                    let mut total_abs_q = 0.0;
+                    let mut weighted_q = Vec2::ZERO;
                     for c in children.iter_mut() {
-                        let (m, p) = compute(c);
-                        total_m += m;
-                        weighted += p * m;
+                        let m = compute(c);
+                        total_m += m.mass;
+                        weighted += m.com * m.mass;
+                        total_q += m.charge;
+                        total_abs_q += m.abs_charge;
+                        weighted_q += m.charge_centre * m.abs_charge;
                     }
                     *mass = total_m.max(0.0);
                     *com = if total_m > 0.0 {
@@ -119,7 +163,19 @@ impl QuadTree {
                     } else {
                         Vec2::ZERO
                     };
-                    (*mass, *com)
+                    *charge = total_q;
+                    *charge_centre = if total_abs_q > 0.0 {
+                        weighted_q / total_abs_q
+                    } else {
+                        *com
+                    };
+                    Moments {
+                        mass: *mass,
+                        com: *com,
+                        charge: *charge,
+                        abs_charge: total_abs_q,
+                        charge_centre: *charge_centre,
+                    }
                 }
             }
         }
@@ -141,24 +197,17 @@ impl QuadTree {
         (depth as f32 / MAX_DEPTH as f32).min(1.0)
     }
 
-    pub fn approx_acc(&self, p: Vec2, g: f32, theta: f32, soft2: f32) -> Vec2 {
-        fn walk(node: &Node, p: Vec2, g: f32, theta2: f32, soft2: f32) -> Vec2 {
+    pub fn approx_acc(&self, p: Vec2, g: f32, theta: f32, soft2: f32, law: &dyn ForceLaw) -> Vec2 {
+        fn walk(node: &Node, p: Vec2, g: f32, theta2: f32, soft2: f32, law: &dyn ForceLaw) -> Vec2 {
             match node {
                 Node::Empty(_) => Vec2::ZERO,
-                Node::Leaf { pos, mass, .. } => {
-                    let r = *pos - p;
-                    let dist2 = r.length_squared() + soft2;
-                    if dist2 == 0.0 {
-                        return Vec2::ZERO;
-                    }
-                    let inv = 1.0 / dist2.sqrt().powi(3);
-                    g * *mass * r * inv
-                }
+                Node::Leaf { pos, mass, .. } => law.pair(*pos - p, *mass, g, soft2),
                 Node::Internal {
                     quad,
                     mass,
                     com,
                     children,
+                    ..
                 } => {
                     if *mass == 0.0 {
                         return Vec2::ZERO;
@@ -166,27 +215,56 @@ impl QuadTree {
                     let r = *com - p;
                     let d = r.length();
                     let s = quad.size();
-                    if d == 0.0 {
+                    if d != 0.0 && (s * s) / (d * d) < theta2 {
+                        law.far_field(r, *mass, g, soft2, s)
+                    } else {
                         let mut a = Vec2::ZERO;
                         for c in children.iter() {
-                            a += walk(c, p, g, theta2, soft2);
+                            a += walk(c, p, g, theta2, soft2, law);
                         }
-                        return a;
+                        a
                     }
-                    if (s * s) / (d * d) < theta2 {
-                        let dist2 = d * d + soft2;
-                        let inv = 1.0 / dist2.sqrt().powi(3);
-                        return g * *mass * r * inv;
+                }
+            }
+        }
+        walk(&self.root, p, g, theta * theta, soft2, law)
+    }
+
+    /// Electric field at `p` for Coulomb constant `k` (points away from positive charge).
+    pub fn approx_field(&self, p: Vec2, k: f32, theta: f32, soft2: f32) -> Vec2 {
+        fn point(q: f32, r: Vec2, k: f32, soft2: f32) -> Vec2 {
+            let dist2 = r.length_squared() + soft2;
+            if dist2 == 0.0 || q == 0.0 {
+                return Vec2::ZERO;
+            }
+            k * q * r / dist2.sqrt().powi(3)
+        }
+        fn walk(node: &Node, p: Vec2, k: f32, theta2: f32, soft2: f32) -> Vec2 {
+            match node {
+                Node::Empty(_) => Vec2::ZERO,
+                Node::Leaf { pos, charge, .. } => point(*charge, p - *pos, k, soft2),
+                Node::Internal {
+                    quad,
+                    charge,
+                    charge_centre,
+                    children,
+                    ..
+                } => {
+                    let r = p - *charge_centre;
+                    let d2 = r.length_squared();
+                    let s = quad.size();
+                    if d2 != 0.0 && (s * s) / d2 < theta2 {
+                        point(*charge, r, k, soft2)
                     } else {
-                        let mut a = Vec2::ZERO;
+                        let mut e = Vec2::ZERO;
                         for c in children.iter() {
-                            a += walk(c, p, g, theta2, soft2);
+                            e += walk(c, p, k, theta2, soft2);
                         }
-                        return a;
+                        e
                     }
                 }
             }
         }
-        walk(&self.root, p, g, theta * theta, soft2)
+        walk(&self.root, p, k, theta * theta, soft2)
     }
 }
@@ -0,0 +1,121 @@
+//! Radiation pressure and stellar winds from luminous bodies.
+//!
+//! Only `Class::Star` bodies shine; luminosity follows a mass–luminosity power
+//! law. Light pushes asteroids and planets outward with an acceleration
+//! proportional to L·(area/mass)/r², so small grains are cleared from the inner
+//! system first, and the wind strips mass from nearby asteroids until they evaporate.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{Body, Class, Player, SimSettings, SimStats};
+
+/// Tuning for [`apply_radiation`].
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RadiationSettings {
+    pub enabled: bool,
+    /// Radiation-pressure constant (acceleration = k·L·r_body²/(m·d²)).
+    pub pressure_k: f32,
+    /// Mass at which a star has unit luminosity.
+    pub reference_mass: f32,
+    /// Exponent of the mass–luminosity relation L = (m / reference_mass)^n.
+    pub luminosity_exponent: f32,
+    /// Wind stripping constant (mass lost per second = k·L·r_body²/d²).
+    pub wind_strip: f32,
+    /// Only bodies lighter than this lose mass to the wind.
+    pub strip_max_mass: f32,
+    /// Bodies stripped below this mass evaporate.
+    pub evaporate_below: f32,
+}
+
+/// Off by default; presets that turn it on launch their belts with the
+/// outward push taken off the central pull.
+impl Default for RadiationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pressure_k: 1.5e6,
+            reference_mass: 1e5,
+            luminosity_exponent: 2.5,
+            wind_strip: 40.0,
+            strip_max_mass: 500.0,
+            evaporate_below: 2.0,
+        }
+    }
+}
+
+/// Luminosity of a body in units of a `reference_mass` star; zero for non-stars.
+pub fn luminosity(class: Class, mass: f32, settings: &RadiationSettings) -> f32 {
+    match class {
+        Class::Star => (mass / settings.reference_mass.max(1.0)).powf(settings.luminosity_exponent),
+        // Black holes swallow their light; planets and asteroids only reflect.
+        Class::BlackHole | Class::Planet | Class::Asteroid => 0.0,
+    }
+}
+
+/// Coefficient `c` of the outward acceleration `c / d²` that light of total
+/// `luminosity` exerts on a body of `mass`; zero when radiation is off or the
+/// body is too heavy to be pushed.
+pub fn pressure_coefficient(luminosity: f32, mass: f32, settings: &RadiationSettings) -> f32 {
+    if !settings.enabled || !matches!(Class::from_mass(mass), Class::Asteroid | Class::Planet) {
+        return 0.0;
+    }
+    let radius = Class::radius_for_mass(mass);
+    settings.pressure_k * luminosity * radius * radius / mass.max(1e-3)
+}
+
+pub(super) fn apply_radiation(
+    mut commands: Commands,
+    settings: Res<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    mut q: Query<(Entity, &mut Body, &Transform, Option<&Player>)>,
+) {
+    let rad = settings.radiation;
+    if !settings.running || !rad.enabled {
+        return;
+    }
+    let sources: Vec<(Entity, Vec2, f32)> = q
+        .iter()
+        .filter_map(|(e, b, t, _)| {
+            let l = luminosity(b.class, b.mass, &rad);
+            (l > 0.0).then(|| (e, t.translation.truncate(), l))
+        })
+        .collect();
+    if sources.is_empty() {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+
+    for (e, mut b, t, player) in &mut q {
+        if !matches!(b.class, Class::Asteroid | Class::Planet) {
+            continue;
+        }
+        let pos = t.translation.truncate();
+        let radius = Class::radius_for_mass(b.mass);
+        let area = radius * radius;
+        let mut push = Vec2::ZERO;
+        let mut flux = 0.0;
+        for &(src, src_pos, l) in &sources {
+            if src == e {
+                continue;
+            }
+            let d = pos - src_pos;
+            let d2 = d.length_squared().max(1.0);
+            push += d / d2.sqrt() * (l / d2);
+            flux += l / d2;
+        }
+        let acc = push * pressure_coefficient(1.0, b.mass, &rad);
+        b.acc += acc;
+
+        if player.is_none() && b.mass < rad.strip_max_mass {
+            b.mass -= rad.wind_strip * flux * area * dt;
+            if b.mass < rad.evaporate_below {
+                commands.entity(e).despawn_recursive();
+                stats.0 = stats.0.saturating_sub(1);
+            } else {
+                b.class = Class::from_mass(b.mass);
+            }
+        }
+    }
+}
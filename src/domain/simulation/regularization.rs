@@ -0,0 +1,373 @@
+//! Two-body regularization for tight, isolated pairs.
+//!
+//! `select_pairs` looks for mutually-nearest massive bodies on a bound orbit
+//! using a [`SpatialHash`] with one cell per `pair_radius`, and keeps those
+//! whose apoapsis fits inside `pair_radius` with nothing else within
+//! `isolation` apoapses of the pair. For the rest of the step a pair is one
+//! composite body: the quadtree holds a single point at its centre of mass,
+//! both members feel the field there, and the centre of mass is kicked and
+//! drifted like any other body. The relative orbit is advanced exactly in
+//! Levi-Civita coordinates, where the Kepler problem becomes a harmonic
+//! oscillator in the fictitious time s (dt = r ds). No softening and no
+//! `max_vel` clamp touch the pair, so close binaries keep their energy.
+
+use bevy::math::DVec2;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::accretion::DiskParticle;
+use super::force_law::ForceLawKind;
+use super::orbits::OrbitalElements;
+use super::sph::GasParticle;
+use super::{Body, Charge, Class, SimSettings, SpatialHash};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegularizationSettings {
+    pub enabled: bool,
+    /// Pairs wider than this (or whose apoapsis is) are left to the tree.
+    pub pair_radius: f32,
+    /// Both members must be at least this heavy.
+    pub min_mass: f32,
+    /// No third body may come within this many apoapses of the pair's centre of mass.
+    pub isolation: f32,
+}
+
+impl Default for RegularizationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pair_radius: 800.0,
+            min_mass: 500.0,
+            isolation: 2.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RegularizedPair {
+    pub primary: Entity,
+    pub secondary: Entity,
+}
+
+/// Pairs integrated in regularized coordinates during the current step.
+#[derive(Resource, Default)]
+pub struct RegularizedPairs {
+    pairs: Vec<RegularizedPair>,
+    /// Member → primary of its pair.
+    members: HashMap<Entity, Entity>,
+}
+
+impl RegularizedPairs {
+    pub fn contains(&self, e: Entity) -> bool {
+        self.members.contains_key(&e)
+    }
+    pub fn primary_of(&self, e: Entity) -> Option<Entity> {
+        self.members.get(&e).copied()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &RegularizedPair> {
+        self.pairs.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Candidate {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    mass: f32,
+    eligible: bool,
+}
+
+pub(super) fn select_pairs(
+    settings: Res<SimSettings>,
+    mut pairs: ResMut<RegularizedPairs>,
+    mut hash: ResMut<SpatialHash>,
+    q: Query<(
+        Entity,
+        &Body,
+        &Transform,
+        Option<&Charge>,
+        Has<GasParticle>,
+        Has<DiskParticle>,
+    )>,
+) {
+    pairs.pairs.clear();
+    pairs.members.clear();
+    let spec = settings.regularization;
+    // The Kepler solution only holds for plain inverse-square gravity.
+    if !spec.enabled || !settings.running || settings.force_law != ForceLawKind::Newtonian {
+        return;
+    }
+    // PN terms act on the relative orbit of black-hole pairs, so those stay with the tree.
+    let pn_holes = settings.post_newtonian.enabled;
+
+    let mut bodies: Vec<Candidate> = q
+        .iter()
+        .map(|(e, b, t, charge, gas, disk)| {
+            // Coulomb forces between the members would be lost in the Kepler solution.
+            let charged = settings.electrostatics && charge.is_some_and(|c| c.0 != 0.0);
+            let pn = pn_holes && b.class == Class::BlackHole;
+            Candidate {
+                entity: e,
+                pos: t.translation.truncate(),
+                vel: b.vel,
+                mass: b.mass,
+                eligible: b.mass >= spec.min_mass && !(gas || disk || charged || pn),
+            }
+        })
+        .collect();
+    bodies.sort_by_key(|c| c.entity);
+    if bodies.iter().filter(|c| c.eligible).count() < 2 {
+        return;
+    }
+    let index: HashMap<Entity, usize> = bodies
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.entity, i))
+        .collect();
+    hash.reset(spec.pair_radius);
+    for c in &bodies {
+        hash.insert(c.entity, c.pos);
+    }
+
+    let nearest = |i: usize| -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        hash.for_each_near(bodies[i].pos, spec.pair_radius, |e| {
+            let j = index[&e];
+            if j == i || !bodies[j].eligible {
+                return;
+            }
+            let d2 = (bodies[j].pos - bodies[i].pos).length_squared();
+            if d2 < spec.pair_radius * spec.pair_radius && best.is_none_or(|(_, b)| d2 < b) {
+                best = Some((j, d2));
+            }
+        });
+        best.map(|(j, _)| j)
+    };
+
+    for i in 0..bodies.len() {
+        if !bodies[i].eligible {
+            continue;
+        }
+        let Some(j) = nearest(i) else {
+            continue;
+        };
+        if j < i || nearest(j) != Some(i) {
+            continue;
+        }
+        let (a, b) = (bodies[i], bodies[j]);
+        let total = a.mass + b.mass;
+        let mu = settings.g * total;
+        let el = OrbitalElements::from_state(a.pos - b.pos, a.vel - b.vel, mu);
+        let Some(apo) = el.apoapsis.filter(|apo| *apo < spec.pair_radius) else {
+            continue;
+        };
+        let com = (a.pos * a.mass + b.pos * b.mass) / total;
+        let reach = spec.isolation * apo;
+        let mut isolated = true;
+        hash.for_each_near(com, reach, |e| {
+            if e != a.entity && e != b.entity {
+                let p = bodies[index[&e]].pos;
+                isolated &= (p - com).length_squared() >= reach * reach;
+            }
+        });
+        if !isolated {
+            continue;
+        }
+        let (primary, secondary) = if a.mass >= b.mass {
+            (a.entity, b.entity)
+        } else {
+            (b.entity, a.entity)
+        };
+        pairs.pairs.push(RegularizedPair { primary, secondary });
+        pairs.members.insert(primary, primary);
+        pairs.members.insert(secondary, primary);
+    }
+}
+
+/// Stumpff functions c0..c3 of `z`.
+fn stumpff(z: f64) -> [f64; 4] {
+    if z > 1e-6 {
+        let s = z.sqrt();
+        [
+            s.cos(),
+            s.sin() / s,
+            (1.0 - s.cos()) / z,
+            (s - s.sin()) / (z * s),
+        ]
+    } else if z < -1e-6 {
+        let s = (-z).sqrt();
+        [
+            s.cosh(),
+            s.sinh() / s,
+            (s.cosh() - 1.0) / -z,
+            (s.sinh() - s) / (-z * s),
+        ]
+    } else {
+        [
+            1.0 - z / 2.0 + z * z / 24.0,
+            1.0 - z / 6.0 + z * z / 120.0,
+            0.5 - z / 24.0 + z * z / 720.0,
+            1.0 / 6.0 - z / 120.0 + z * z / 5040.0,
+        ]
+    }
+}
+
+fn cmul(a: DVec2, b: DVec2) -> DVec2 {
+    DVec2::new(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x)
+}
+
+fn conj(a: DVec2) -> DVec2 {
+    DVec2::new(a.x, -a.y)
+}
+
+fn csqrt(a: DVec2) -> DVec2 {
+    let r = a.length();
+    let half = 0.5 * a.y.atan2(a.x);
+    DVec2::new(half.cos(), half.sin()) * r.sqrt()
+}
+
+/// Advances the relative orbit (`x`, `v`) with gravitational parameter `mu` by
+/// `dt`. Returns `None` if the fictitious-time equation does not converge.
+fn kepler_step(x: DVec2, v: DVec2, mu: f64, dt: f64) -> Option<(DVec2, DVec2)> {
+    let r0 = x.length();
+    if r0 <= 0.0 || mu <= 0.0 {
+        return None;
+    }
+    let h = 0.5 * v.length_squared() - mu / r0;
+    let eta0 = x.dot(v);
+
+    // Levi-Civita state: x = u², u' = du/ds = ẋ·ū / 2.
+    let u0 = csqrt(x);
+    let du0 = cmul(v, conj(u0)) * 0.5;
+
+    // Solve t(s) = dt, where r = |u|² obeys r'' = 2h·r + μ.
+    let beta = -2.0 * h;
+    let mut s = dt / r0;
+    let mut converged = false;
+    for _ in 0..50 {
+        let [c0, c1, c2, c3] = stumpff(beta * s * s);
+        let t = r0 * s * c1 + eta0 * s * s * c2 + mu * s * s * s * c3;
+        let r = r0 * c0 + eta0 * s * c1 + mu * s * s * c2;
+        if r <= 0.0 {
+            return None;
+        }
+        let ds = (t - dt) / r;
+        s -= ds;
+        if ds.abs() <= 1e-12 * s.abs().max(1e-12) {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return None;
+    }
+
+    // The oscillator u'' = (h/2)·u, advanced by s.
+    let alpha = -0.5 * h;
+    let [c0, c1, _, _] = stumpff(alpha * s * s);
+    let u = u0 * c0 + du0 * (s * c1);
+    let du = u0 * (-alpha * s * c1) + du0 * c0;
+    let r = u.length_squared();
+    if r <= 0.0 {
+        return None;
+    }
+    Some((cmul(u, u), cmul(du, u) * (2.0 / r)))
+}
+
+struct PairState {
+    pos: [Vec2; 2],
+    vel: [Vec2; 2],
+    mass: [f32; 2],
+    acc: Vec2,
+}
+
+fn pair_state(q: &Query<(&mut Body, &mut Transform)>, pair: &RegularizedPair) -> Option<PairState> {
+    let (a, ta) = q.get(pair.primary).ok()?;
+    let (b, tb) = q.get(pair.secondary).ok()?;
+    let total = a.mass + b.mass;
+    Some(PairState {
+        pos: [ta.translation.truncate(), tb.translation.truncate()],
+        vel: [a.vel, b.vel],
+        mass: [a.mass, b.mass],
+        // Mutual forces cancel in the mass-weighted mean, leaving only the external pull.
+        acc: (a.acc * a.mass + b.acc * b.mass) / total,
+    })
+}
+
+/// First half-kick and drift for regularized pairs (they are skipped by `kick1_drift`).
+pub(super) fn drift_pairs(
+    settings: Res<SimSettings>,
+    pairs: Res<RegularizedPairs>,
+    mut q: Query<(&mut Body, &mut Transform)>,
+) {
+    if !settings.running || pairs.pairs.is_empty() {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    for pair in &pairs.pairs {
+        let Some(s) = pair_state(&q, pair) else {
+            continue;
+        };
+        let total = s.mass[0] + s.mass[1];
+        let kick = s.acc * dt * 0.5;
+        let vel = [s.vel[0] + kick, s.vel[1] + kick];
+        let com = (s.pos[0] * s.mass[0] + s.pos[1] * s.mass[1]) / total;
+        let com_vel = (vel[0] * s.mass[0] + vel[1] * s.mass[1]) / total;
+
+        let mu = (settings.g * total) as f64;
+        let (rel_pos, rel_vel) = match kepler_step(
+            (s.pos[0] - s.pos[1]).as_dvec2(),
+            (vel[0] - vel[1]).as_dvec2(),
+            mu,
+            dt as f64,
+        ) {
+            Some((x, v)) => (x.as_vec2(), v.as_vec2()),
+            // Fall back to a plain drift of the relative orbit.
+            None => (
+                s.pos[0] - s.pos[1] + (vel[0] - vel[1]) * dt,
+                vel[0] - vel[1],
+            ),
+        };
+
+        let new_com = com + com_vel * dt;
+        let share = [s.mass[1] / total, -s.mass[0] / total];
+        for (k, e) in [pair.primary, pair.secondary].into_iter().enumerate() {
+            if let Ok((mut b, mut t)) = q.get_mut(e) {
+                b.vel = com_vel + rel_vel * share[k];
+                let p = new_com + rel_pos * share[k];
+                t.translation.x = p.x;
+                t.translation.y = p.y;
+            }
+        }
+    }
+}
+
+/// Second half-kick for regularized pairs (skipped by `kick2`): the centre of
+/// mass takes the external pull, the relative orbit is left untouched.
+pub(super) fn kick_pairs(
+    settings: Res<SimSettings>,
+    pairs: Res<RegularizedPairs>,
+    mut q: Query<(&mut Body, &mut Transform)>,
+) {
+    if !settings.running || pairs.pairs.is_empty() {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    for pair in &pairs.pairs {
+        let Some(s) = pair_state(&q, pair) else {
+            continue;
+        };
+        let kick = s.acc * dt * 0.5;
+        for e in [pair.primary, pair.secondary] {
+            if let Ok((mut b, _)) = q.get_mut(e) {
+                b.vel += kick;
+            }
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! Input-log replays.
+//!
+//! Recording starts from a reset, so the run is fully described by its seed
+//! and settings; from then on every input the tick sees is logged against the
+//! tick it was applied on: thrust changes, player bursts, resets (with the
+//! seed they drew), time-scale and pause changes, and any other edit to the
+//! physics settings. Playing the file back resets to the same seed and feeds
+//! the logged inputs in at the same ticks while ignoring live ones, so the
+//! simulation retraces the recorded run; at the end the state hash is checked
+//! against the one the recording finished with. A replay file plus the build
+//! that made it is enough to reproduce a physics bug.
+//!
+//! Only the sequential schedule orders every system, so recording and playback
+//! switch the simulation to deterministic mode first and keep it there.
+//!
+//! Alongside the inputs, recordings sample the player's position, mass and
+//! score every [`TRACK_EVERY`] ticks of the first run; that track is what
+//! ghosts follow, so it needs no re-simulation. Files from version 1 have none.
+//!
+//! [`handle_commands`] starts and stops recordings and playbacks once per
+//! frame; [`apply_inputs`] runs at the start of every tick and feeds it its
+//! inputs.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::nebula::Nebula;
+use super::state_hash::{settings_hash, StateHash};
+use super::{
+    next_seed, reset_world, AppState, Body, BurstRequest, Class, Player, PlayerInput, ResetEvent,
+    Scenario, SimClock, SimSettings, SimState, SimStats, SpawnBurst,
+};
+
+/// Version 2 added the player track.
+pub const REPLAY_VERSION: u32 = 2;
+
+/// Ticks between samples of the player track.
+pub const TRACK_EVERY: u64 = 4;
+
+pub const REPLAY_DIR: &str = "replays";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ReplayInput {
+    /// Thrust held from this tick until the next `Thrust` entry.
+    Thrust {
+        dir: Vec2,
+        boost: bool,
+    },
+    Burst(SpawnBurst),
+    /// Restart the scenario with `seed`.
+    Reset {
+        seed: u64,
+    },
+    TimeScale(f32),
+    /// `true` pauses, `false` resumes.
+    Pause(bool),
+    /// Any other change to the physics settings; display-only fields are ignored.
+    Settings(Box<SimSettings>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// Ticks since the recording began (a reset does not restart the count).
+    pub tick: u64,
+    pub input: ReplayInput,
+}
+
+/// What the replay browser shows without reading the whole log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    /// Unix time the recording started.
+    pub recorded_at: u64,
+    pub scenario: Scenario,
+    pub seed: u64,
+    pub ticks: u64,
+    /// Sim seconds covered, not counting pauses.
+    pub duration: f32,
+    pub inputs: usize,
+    /// State hash after the last recorded tick.
+    pub final_hash: u64,
+    #[serde(default)]
+    pub track_samples: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub header: ReplayHeader,
+    /// Settings right after the opening reset.
+    pub settings: SimSettings,
+    pub entries: Vec<ReplayEntry>,
+    #[serde(default)]
+    pub track: Vec<TrackSample>,
+}
+
+/// The player at one point of a recorded run.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TrackSample {
+    /// Sim seconds since the run's reset.
+    pub time: f32,
+    pub pos: Vec2,
+    pub mass: f32,
+    pub class: Class,
+    pub score: f32,
+}
+
+impl ReplayFile {
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: ReplayFile = ron::from_str(&text).map_err(|e| e.to_string())?;
+        if file.header.version > REPLAY_VERSION {
+            return Err(format!(
+                "replay version {} is newer than this build's {REPLAY_VERSION}",
+                file.header.version
+            ));
+        }
+        Ok(file)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new().depth_limit(3))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
+
+/// Replay files in [`REPLAY_DIR`], newest first; unreadable ones carry their error.
+pub fn list_replays() -> Vec<(PathBuf, Result<ReplayHeader, String>)> {
+    let Ok(dir) = std::fs::read_dir(REPLAY_DIR) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(PathBuf, Result<ReplayHeader, String>)> = dir
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "ron"))
+        .map(|p| {
+            let header = ReplayFile::read(&p).map(|f| f.header);
+            (p, header)
+        })
+        .collect();
+    found.sort_by(|a, b| b.0.cmp(&a.0));
+    found
+}
+
+pub(super) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[derive(Event, Clone, Debug)]
+pub enum ReplayCommand {
+    /// Reset and start logging.
+    Record,
+    /// Stop recording (and save) or playing.
+    Stop,
+    Play(PathBuf),
+    /// Play in the replay viewer.
+    View(PathBuf),
+}
+
+pub struct Recording {
+    file: ReplayFile,
+    tick: u64,
+    input: PlayerInput,
+    /// Settings as of the last logged change.
+    settings: SimSettings,
+    /// Set once the first run ends (a reset); later runs are not tracked.
+    track_closed: bool,
+}
+
+pub struct Playback {
+    pub path: PathBuf,
+    file: ReplayFile,
+    pub tick: u64,
+    cursor: usize,
+    input: PlayerInput,
+    /// Settings the log has set so far; live edits are reverted to these.
+    settings: SimSettings,
+}
+
+/// Where a playback stands: with a snapshot of the same tick, enough to carry
+/// on from there.
+#[derive(Clone, Debug)]
+pub struct PlaybackPosition {
+    pub tick: u64,
+    cursor: usize,
+    input: PlayerInput,
+    settings: SimSettings,
+}
+
+impl Playback {
+    pub fn header(&self) -> &ReplayHeader {
+        &self.file.header
+    }
+
+    pub fn position(&self) -> PlaybackPosition {
+        PlaybackPosition {
+            tick: self.tick,
+            cursor: self.cursor,
+            input: self.input,
+            settings: self.settings.clone(),
+        }
+    }
+
+    pub fn seek(&mut self, position: &PlaybackPosition) {
+        self.tick = position.tick;
+        self.cursor = position.cursor;
+        self.input = position.input;
+        self.settings = position.settings.clone();
+    }
+}
+
+#[derive(Default)]
+pub enum ReplayMode {
+    #[default]
+    Idle,
+    Recording(Box<Recording>),
+    Playing(Box<Playback>),
+}
+
+#[derive(Resource, Default)]
+pub struct Replay {
+    pub mode: ReplayMode,
+    /// Outcome of the last recording or playback, for the HUD.
+    pub status: Option<String>,
+    /// `Record` and `Play` held back a frame while the schedule switches.
+    deferred: Vec<ReplayCommand>,
+}
+
+impl Replay {
+    /// Ticks recorded so far, while recording.
+    pub fn recorded_ticks(&self) -> Option<u64> {
+        match &self.mode {
+            ReplayMode::Recording(rec) => Some(rec.tick),
+            _ => None,
+        }
+    }
+
+    pub fn playback(&self) -> Option<&Playback> {
+        match &self.mode {
+            ReplayMode::Playing(pb) => Some(pb),
+            _ => None,
+        }
+    }
+
+    pub fn playback_mut(&mut self) -> Option<&mut Playback> {
+        match &mut self.mode {
+            ReplayMode::Playing(pb) => Some(pb),
+            _ => None,
+        }
+    }
+}
+
+/// The logged form of a settings change from `old` to `new`, if it matters
+/// to the simulation.
+pub(super) fn settings_change(old: &SimSettings, new: &SimSettings) -> Option<ReplayInput> {
+    if settings_hash(old) == settings_hash(new) {
+        return None;
+    }
+    let only = |field: fn(&mut SimSettings, &SimSettings)| {
+        let mut probe = old.clone();
+        field(&mut probe, new);
+        settings_hash(&probe) == settings_hash(new)
+    };
+    Some(if only(|s, n| s.time_scale = n.time_scale) {
+        ReplayInput::TimeScale(new.time_scale)
+    } else if only(|s, n| s.running = n.running) {
+        ReplayInput::Pause(!new.running)
+    } else {
+        ReplayInput::Settings(Box::new(new.clone()))
+    })
+}
+
+/// Handles [`ReplayCommand`]s, once per frame so they also work while the
+/// viewer is paused. `Record`, `Play` and `View` first switch the simulation
+/// to deterministic mode, which takes effect a frame later.
+pub(super) fn handle_commands(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut ev_command: EventReader<ReplayCommand>,
+    mut input: ResMut<PlayerInput>,
+    mut settings: ResMut<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    state_hash: Res<StateHash>,
+    schedule: Res<State<SimState>>,
+    mut next_schedule: ResMut<NextState<SimState>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    doomed: Query<Entity, Or<(With<Body>, With<Nebula>)>>,
+) {
+    let replay = replay.as_mut();
+    let sequential = *schedule.get() == SimState::Sequential;
+    let pending: Vec<ReplayCommand> = replay
+        .deferred
+        .drain(..)
+        .chain(ev_command.read().cloned())
+        .collect();
+    for command in pending {
+        if !sequential && !matches!(command, ReplayCommand::Stop) {
+            settings.deterministic = true;
+            next_schedule.set(SimState::Sequential);
+            replay.deferred.push(command);
+            continue;
+        }
+        match command {
+            ReplayCommand::Record => {
+                let seed = next_seed(&settings);
+                reset_world(&mut commands, &doomed, &mut stats, &mut settings, seed);
+                replay.mode = ReplayMode::Recording(Box::new(Recording {
+                    file: ReplayFile {
+                        header: ReplayHeader {
+                            version: REPLAY_VERSION,
+                            recorded_at: unix_now(),
+                            scenario: settings.scenario,
+                            seed,
+                            ticks: 0,
+                            duration: 0.0,
+                            inputs: 0,
+                            final_hash: 0,
+                            track_samples: 0,
+                        },
+                        settings: settings.clone(),
+                        entries: Vec::new(),
+                        track: Vec::new(),
+                    },
+                    tick: 0,
+                    input: PlayerInput::default(),
+                    settings: settings.clone(),
+                    track_closed: false,
+                }));
+                replay.status = Some(format!("Recording (seed {seed})"));
+            }
+            ReplayCommand::Stop => match std::mem::take(&mut replay.mode) {
+                ReplayMode::Recording(rec) => {
+                    let Recording { mut file, tick, .. } = *rec;
+                    file.header.ticks = tick;
+                    file.header.inputs = file.entries.len();
+                    file.header.final_hash = state_hash.value;
+                    file.header.track_samples = file.track.len();
+                    let path = Path::new(REPLAY_DIR)
+                        .join(format!("replay-{}.ron", file.header.recorded_at));
+                    replay.status = Some(match file.write(&path) {
+                        Ok(()) => format!("Saved {tick} ticks to {}", path.display()),
+                        Err(e) => format!("Could not save {}: {e}", path.display()),
+                    });
+                }
+                ReplayMode::Playing(pb) => {
+                    replay.status = Some(format!("Stopped playback at tick {}", pb.tick));
+                }
+                ReplayMode::Idle => {}
+            },
+            ReplayCommand::Play(ref path) | ReplayCommand::View(ref path) => {
+                match ReplayFile::read(path) {
+                    Ok(file) => {
+                        let display = settings.clone();
+                        settings.scenario = file.header.scenario;
+                        reset_world(
+                            &mut commands,
+                            &doomed,
+                            &mut stats,
+                            &mut settings,
+                            file.header.seed,
+                        );
+                        *settings = file.settings.clone();
+                        settings.copy_display(&display);
+                        *input = PlayerInput::default();
+                        if matches!(command, ReplayCommand::View(_)) {
+                            // The viewer's camera is free.
+                            settings.follow_player = false;
+                            // The viewer restarts a replay to seek before its
+                            // first keyframe; that must not reset the viewer.
+                            if *state.get() != AppState::Viewing {
+                                next_state.set(AppState::Viewing);
+                            }
+                            replay.status = Some(format!("Viewing {}", path.display()));
+                        } else {
+                            replay.status = Some(format!("Playing {}", path.display()));
+                        }
+                        replay.mode = ReplayMode::Playing(Box::new(Playback {
+                            path: path.clone(),
+                            settings: file.settings.clone(),
+                            file,
+                            tick: 0,
+                            cursor: 0,
+                            input: PlayerInput::default(),
+                        }));
+                    }
+                    Err(e) => {
+                        replay.status = Some(format!("Could not read {}: {e}", path.display()));
+                    }
+                }
+            }
+        }
+    }
+
+    if !matches!(replay.mode, ReplayMode::Idle) {
+        if !settings.deterministic {
+            settings.deterministic = true;
+        }
+        if !sequential {
+            next_schedule.set(SimState::Sequential);
+        }
+    }
+}
+
+/// Turns this tick's inputs into simulation changes: live ones while idle or
+/// recording (logging them when recording), logged ones during playback.
+pub(super) fn apply_inputs(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut ev_reset: EventReader<ResetEvent>,
+    mut ev_burst: EventReader<BurstRequest>,
+    mut ev_spawn: EventWriter<SpawnBurst>,
+    mut input: ResMut<PlayerInput>,
+    mut settings: ResMut<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    state_hash: Res<StateHash>,
+    doomed: Query<Entity, Or<(With<Body>, With<Nebula>)>>,
+) {
+    let replay = replay.as_mut();
+    match &mut replay.mode {
+        ReplayMode::Idle => {
+            if ev_reset.read().count() > 0 {
+                let seed = next_seed(&settings);
+                reset_world(&mut commands, &doomed, &mut stats, &mut settings, seed);
+            }
+            for burst in ev_burst.read() {
+                ev_spawn.send(burst.0);
+            }
+        }
+        ReplayMode::Recording(rec) => {
+            let tick = rec.tick;
+            let log = &mut rec.file.entries;
+            if ev_reset.read().count() > 0 {
+                let seed = next_seed(&settings);
+                reset_world(&mut commands, &doomed, &mut stats, &mut settings, seed);
+                log.push(ReplayEntry {
+                    tick,
+                    input: ReplayInput::Reset { seed },
+                });
+                rec.settings = settings.clone();
+            }
+            if let Some(change) = settings_change(&rec.settings, &settings) {
+                log.push(ReplayEntry {
+                    tick,
+                    input: change,
+                });
+                rec.settings = settings.clone();
+            }
+            if *input != rec.input {
+                log.push(ReplayEntry {
+                    tick,
+                    input: ReplayInput::Thrust {
+                        dir: input.thrust,
+                        boost: input.boost,
+                    },
+                });
+                rec.input = *input;
+            }
+            for burst in ev_burst.read() {
+                ev_spawn.send(burst.0);
+                log.push(ReplayEntry {
+                    tick,
+                    input: ReplayInput::Burst(burst.0),
+                });
+            }
+            if settings.running {
+                rec.file.header.duration += settings.dt * settings.time_scale;
+            }
+            rec.tick += 1;
+        }
+        ReplayMode::Playing(pb) => {
+            // Live inputs are dropped; the log alone drives the run.
+            ev_reset.clear();
+            ev_burst.clear();
+            if pb.tick == pb.file.header.ticks {
+                let expected = pb.file.header.final_hash;
+                replay.status = Some(if state_hash.value == expected {
+                    format!("Replay finished; state matches the recording ({expected:016x})")
+                } else {
+                    format!(
+                        "Replay diverged: state hash {:016x}, recorded {expected:016x}",
+                        state_hash.value
+                    )
+                });
+                replay.mode = ReplayMode::Idle;
+                return;
+            }
+            while let Some(entry) = pb.file.entries.get(pb.cursor) {
+                if entry.tick != pb.tick {
+                    break;
+                }
+                match &entry.input {
+                    ReplayInput::Thrust { dir, boost } => {
+                        pb.input = PlayerInput {
+                            thrust: *dir,
+                            boost: *boost,
+                        };
+                    }
+                    ReplayInput::Burst(burst) => {
+                        ev_spawn.send(*burst);
+                    }
+                    ReplayInput::Reset { seed } => {
+                        reset_world(&mut commands, &doomed, &mut stats, &mut pb.settings, *seed);
+                    }
+                    ReplayInput::TimeScale(scale) => pb.settings.time_scale = *scale,
+                    ReplayInput::Pause(paused) => pb.settings.running = !paused,
+                    ReplayInput::Settings(logged) => pb.settings = (**logged).clone(),
+                }
+                pb.cursor += 1;
+            }
+            let display = settings.clone();
+            *settings = pb.settings.clone();
+            settings.copy_display(&display);
+            *input = pb.input;
+            pb.tick += 1;
+        }
+    }
+}
+
+/// Samples the player's track every [`TRACK_EVERY`] ticks while recording.
+pub(super) fn record_track(
+    mut replay: ResMut<Replay>,
+    clock: Res<SimClock>,
+    settings: Res<SimSettings>,
+    player_q: Query<(&Transform, &Body, &Player)>,
+) {
+    let ReplayMode::Recording(rec) = &mut replay.mode else {
+        return;
+    };
+    if rec.track_closed || !settings.running || !rec.tick.is_multiple_of(TRACK_EVERY) {
+        return;
+    }
+    if rec
+        .file
+        .track
+        .last()
+        .is_some_and(|s| clock.elapsed < s.time)
+    {
+        rec.track_closed = true;
+        return;
+    }
+    if let Ok((transform, body, player)) = player_q.get_single() {
+        rec.file.track.push(TrackSample {
+            time: clock.elapsed,
+            pos: transform.translation.truncate(),
+            mass: body.mass,
+            class: body.class,
+            score: player.score,
+        });
+    }
+}
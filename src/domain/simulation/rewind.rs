@@ -0,0 +1,171 @@
+//! Rewinding the sandbox.
+//!
+//! While the simulation runs, a [`Snapshot`] of the world is kept in memory
+//! every `every` ticks, oldest first, dropping the oldest once they exceed the
+//! memory budget. Holding the rewind key pauses the tick and restores one
+//! keyframe further back each frame; releasing it discards the keyframes after
+//! the one shown and lets the simulation carry on from there, so everything
+//! after it is re-simulated as a new branch. Bodies keep their [`BodyId`]
+//! across a restore, so anything tracked by id survives rewinding past
+//! collisions that despawned it.
+//!
+//! Rewinding is off while a replay is recording or playing, since the log
+//! cannot follow the world backwards.
+//!
+//! [`BodyId`]: super::BodyId
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use super::replay::{Replay, ReplayMode};
+use super::snapshot::{self, SavedBody, SavedNebula, Snapshot, SnapshotStatus};
+use super::{SimClock, SimSettings};
+
+struct Keyframe {
+    snapshot: Snapshot,
+    bytes: usize,
+}
+
+/// Rough in-memory size of a snapshot, for the budget.
+fn approx_bytes(snapshot: &Snapshot) -> usize {
+    size_of::<Snapshot>()
+        + snapshot.bodies.len() * size_of::<SavedBody>()
+        + snapshot.nebulae.len() * size_of::<SavedNebula>()
+}
+
+#[derive(Resource)]
+pub struct Rewind {
+    pub enabled: bool,
+    /// Keep a keyframe every this many ticks.
+    pub every: u32,
+    /// Memory the keyframes may use, in MiB.
+    pub budget_mib: f32,
+    /// Set by the controls while the rewind key is down.
+    pub held: bool,
+    keyframes: VecDeque<Keyframe>,
+    bytes: usize,
+    /// Keyframe currently shown while scrubbing.
+    cursor: Option<usize>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            every: 15,
+            budget_mib: 64.0,
+            held: false,
+            keyframes: VecDeque::new(),
+            bytes: 0,
+            cursor: None,
+        }
+    }
+}
+
+impl Rewind {
+    pub fn keyframe_count(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Sim seconds between the oldest keyframe and the newest.
+    pub fn span(&self) -> f32 {
+        match (self.keyframes.front(), self.keyframes.back()) {
+            (Some(first), Some(last)) => last.snapshot.clock.elapsed - first.snapshot.clock.elapsed,
+            _ => 0.0,
+        }
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Drops keyframes from `index` on.
+    fn truncate(&mut self, index: usize) {
+        for dropped in self.keyframes.drain(index..) {
+            self.bytes -= dropped.bytes;
+        }
+    }
+
+    fn push(&mut self, snapshot: Snapshot) {
+        // A reset or a loaded snapshot starts a new timeline from this tick.
+        let tick = snapshot.clock.tick;
+        let stale = self
+            .keyframes
+            .iter()
+            .position(|k| k.snapshot.clock.tick >= tick)
+            .unwrap_or(self.keyframes.len());
+        self.truncate(stale);
+
+        let bytes = approx_bytes(&snapshot);
+        self.keyframes.push_back(Keyframe { snapshot, bytes });
+        self.bytes += bytes;
+        let budget = (self.budget_mib.max(0.0) * 1024.0 * 1024.0) as usize;
+        while self.bytes > budget && self.keyframes.len() > 1 {
+            let dropped = self.keyframes.pop_front().expect("more than one keyframe");
+            self.bytes -= dropped.bytes;
+        }
+    }
+}
+
+/// Run condition for the tick: false while scrubbing.
+pub(super) fn not_rewinding(rewind: Res<Rewind>) -> bool {
+    rewind.cursor.is_none()
+}
+
+/// Keeps a keyframe every `every` ticks while the simulation runs.
+pub(super) fn record_keyframe(world: &mut World) {
+    let rewind = world.resource::<Rewind>();
+    let tick = world.resource::<SimClock>().tick;
+    if !rewind.enabled
+        || !world.resource::<SimSettings>().running
+        || !tick.is_multiple_of(u64::from(rewind.every.max(1)))
+        || !matches!(world.resource::<Replay>().mode, ReplayMode::Idle)
+    {
+        return;
+    }
+    let keyframe = snapshot::capture(world);
+    world.resource_mut::<Rewind>().push(keyframe);
+}
+
+/// Steps back one keyframe per frame while the key is held, and cuts the
+/// timeline at the shown keyframe on release. Runs before the tick, which is
+/// skipped while scrubbing so the restored state is what gets drawn.
+pub(super) fn scrub(world: &mut World) {
+    let idle = matches!(world.resource::<Replay>().mode, ReplayMode::Idle);
+    let now = world.resource::<SimClock>().tick;
+    let mut rewind = world.resource_mut::<Rewind>();
+    let held = rewind.held && rewind.enabled && idle;
+    let target = match (held, rewind.cursor) {
+        (false, None) => return,
+        (false, Some(shown)) => {
+            rewind.truncate(shown + 1);
+            rewind.cursor = None;
+            return;
+        }
+        (true, None) => {
+            let Some(newest) = rewind.keyframes.back() else {
+                return;
+            };
+            // The newest keyframe may be this very tick; start one further back.
+            let last = rewind.keyframes.len() - 1;
+            if newest.snapshot.clock.tick >= now {
+                last.saturating_sub(1)
+            } else {
+                last
+            }
+        }
+        (true, Some(0)) => return,
+        (true, Some(shown)) => shown - 1,
+    };
+    rewind.cursor = Some(target);
+    world.resource_scope(|world, rewind: Mut<Rewind>| {
+        if let Err(e) = snapshot::restore(world, &rewind.keyframes[target].snapshot) {
+            world.resource_mut::<SnapshotStatus>().message = Some(format!("Rewind: {e}"));
+        }
+    });
+}
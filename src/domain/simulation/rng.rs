@@ -0,0 +1,89 @@
+//! The single source of randomness for the simulation.
+//!
+//! [`SimRng`] keeps one ChaCha8 generator per [`RngStream`]. All of them are
+//! keyed by the run's seed but sit on separate ChaCha streams, so an extra
+//! debris burst never shifts what the hazard roller or world generation draw
+//! next. The resource is rebuilt from `SimSettings::seed` on every reset: the
+//! same seed and the same inputs give the same random decisions.
+
+use bevy::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Named sub-streams of [`SimRng`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RngStream {
+    /// Initial conditions: belts, clusters, galaxies, nebulae and gas.
+    Generation,
+    Hazards,
+    /// Debris bursts, whether dragged out by the player or sent by a hazard.
+    Bursts,
+    #[allow(dead_code)] // claimed up front so adding AI later leaves the other streams alone
+    Ai,
+}
+
+/// Number of [`RngStream`]s. A new stream goes at the end, so the others keep
+/// their ChaCha stream ids.
+pub const STREAMS: usize = 4;
+
+impl RngStream {
+    const ALL: [RngStream; STREAMS] = [
+        RngStream::Generation,
+        RngStream::Hazards,
+        RngStream::Bursts,
+        RngStream::Ai,
+    ];
+}
+
+#[derive(Resource, Clone)]
+pub struct SimRng {
+    seed: u64,
+    streams: [ChaCha8Rng; STREAMS],
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: RngStream::ALL.map(|s| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(s as u64);
+                rng
+            }),
+        }
+    }
+
+    /// Rebuilds the generators for `seed`, each advanced to the word position
+    /// recorded by [`SimRng::word_positions`].
+    pub fn restore(seed: u64, word_positions: [u128; STREAMS]) -> Self {
+        let mut rng = Self::new(seed);
+        for (stream, pos) in rng.streams.iter_mut().zip(word_positions) {
+            stream.set_word_pos(pos);
+        }
+        rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        &mut self.streams[stream as usize]
+    }
+
+    /// How far each stream has advanced, in 32-bit words.
+    pub fn word_positions(&self) -> [u128; STREAMS] {
+        self.streams.each_ref().map(|s| s.get_word_pos())
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// A fresh seed from OS entropy, for runs that are not pinned to a seed.
+pub fn entropy_seed() -> u64 {
+    rand::thread_rng().next_u64()
+}
@@ -0,0 +1,280 @@
+//! Moons: bodies that settle into orbit around the player (or around the
+//! player's own moons) and stay there.
+//!
+//! `track_satellites` follows every body the [`Orbits`] pass reports as bound
+//! to the player's family, counting how many times it has swept around its
+//! parent. After `capture_orbits` revolutions it becomes a [`Satellite`], the
+//! player scores, and a [`SatelliteCaptured`] event goes out. Satellites that
+//! stay unbound for `escape_grace` ticks, or vanish in a collision, are
+//! released with a [`SatelliteLost`] event. Tracks are saved with snapshots,
+//! so loading one neither loses moons nor scores their capture again.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::TAU;
+
+use super::orbits::Orbits;
+use super::{Body, BodyId, Player, SimSettings};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SatelliteSettings {
+    /// Full revolutions a body must complete before it counts as a moon.
+    pub capture_orbits: f32,
+    /// Ticks a moon may spend unbound (or around another attractor) before it is lost.
+    pub escape_grace: u32,
+    /// Score for a capture, multiplied by the moon's class rarity.
+    pub capture_score: f32,
+    /// Score for every further revolution of a moon, multiplied by its class rarity.
+    pub orbit_score: f32,
+}
+
+impl Default for SatelliteSettings {
+    fn default() -> Self {
+        Self {
+            capture_orbits: 2.0,
+            escape_grace: 30,
+            capture_score: 500.0,
+            orbit_score: 50.0,
+        }
+    }
+}
+
+/// A captured moon of `parent` (the player or one of its moons).
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Satellite {
+    pub parent: Entity,
+    /// Revolutions completed since tracking began.
+    pub orbits: f32,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SatelliteCaptured {
+    pub satellite: Entity,
+    pub parent: Entity,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SatelliteLossReason {
+    /// Left its parent's gravitational grip.
+    Escaped,
+    /// Disappeared, absorbed by its parent or something else.
+    Collided,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SatelliteLost {
+    pub satellite: Entity,
+    pub parent: Entity,
+    pub reason: SatelliteLossReason,
+}
+
+#[derive(Clone, Copy)]
+struct Track {
+    parent: Entity,
+    angle: f32,
+    swept: f32,
+    unbound_ticks: u32,
+    captured: bool,
+}
+
+/// Bodies being followed around the player's family, captured or not. Reset
+/// with the world; a loaded snapshot brings its own.
+#[derive(Resource, Default)]
+pub(super) struct SatelliteTracks(HashMap<Entity, Track>);
+
+/// A body's track as written into a snapshot.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SavedTrack {
+    pub parent: BodyId,
+    pub angle: f32,
+    pub swept: f32,
+    pub unbound_ticks: u32,
+    pub captured: bool,
+}
+
+impl SatelliteTracks {
+    /// `e`'s track, if it has one and its parent has an id.
+    pub(super) fn save(&self, e: Entity, ids: &HashMap<Entity, BodyId>) -> Option<SavedTrack> {
+        let track = self.0.get(&e)?;
+        Some(SavedTrack {
+            parent: *ids.get(&track.parent)?,
+            angle: track.angle,
+            swept: track.swept,
+            unbound_ticks: track.unbound_ticks,
+            captured: track.captured,
+        })
+    }
+
+    /// Tracks `e` around `parent` from `saved`, returning its [`Satellite`]
+    /// if it had been captured.
+    pub(super) fn restore(
+        &mut self,
+        e: Entity,
+        parent: Entity,
+        saved: &SavedTrack,
+    ) -> Option<Satellite> {
+        self.0.insert(
+            e,
+            Track {
+                parent,
+                angle: saved.angle,
+                swept: saved.swept,
+                unbound_ticks: saved.unbound_ticks,
+                captured: saved.captured,
+            },
+        );
+        saved.captured.then_some(Satellite {
+            parent,
+            orbits: saved.swept.abs() / TAU,
+        })
+    }
+}
+
+pub(super) fn track_satellites(
+    mut commands: Commands,
+    settings: Res<SimSettings>,
+    orbits: Res<Orbits>,
+    mut tracks: ResMut<SatelliteTracks>,
+    mut captured: EventWriter<SatelliteCaptured>,
+    mut lost: EventWriter<SatelliteLost>,
+    mut player_q: Query<(Entity, &mut Player)>,
+    bodies: Query<(&Body, &Transform)>,
+    mut satellites: Query<&mut Satellite>,
+) {
+    let spec = settings.satellites;
+    let tracks = &mut tracks.0;
+    let Ok((player_entity, mut player)) = player_q.get_single_mut() else {
+        tracks.clear();
+        return;
+    };
+
+    // The player's family: the player plus every moon hanging off it.
+    let mut family: HashSet<Entity> = HashSet::from([player_entity]);
+    let mut entities: Vec<Entity> = tracks.keys().copied().collect();
+    entities.sort();
+    loop {
+        let before = family.len();
+        for e in &entities {
+            let t = tracks[e];
+            if t.captured && family.contains(&t.parent) {
+                family.insert(*e);
+            }
+        }
+        if family.len() == before {
+            break;
+        }
+    }
+
+    // New candidates: bound to a family member and not yet tracked.
+    let mut fresh: Vec<(Entity, Entity)> = orbits
+        .iter()
+        .filter(|(e, o)| {
+            o.kind.is_bound() && family.contains(&o.attractor) && !tracks.contains_key(e)
+        })
+        .map(|(e, o)| (e, o.attractor))
+        .collect();
+    fresh.sort();
+    for (e, parent) in fresh {
+        if e == player_entity {
+            continue;
+        }
+        let (Ok((_, t)), Ok((_, pt))) = (bodies.get(e), bodies.get(parent)) else {
+            continue;
+        };
+        let rel = t.translation.truncate() - pt.translation.truncate();
+        tracks.insert(
+            e,
+            Track {
+                parent,
+                angle: rel.y.atan2(rel.x),
+                swept: 0.0,
+                unbound_ticks: 0,
+                captured: false,
+            },
+        );
+    }
+
+    let mut entities: Vec<Entity> = tracks.keys().copied().collect();
+    entities.sort();
+    for e in entities {
+        let mut track = tracks[&e];
+        let alive = bodies.get(e).ok();
+        let parent_alive = bodies.get(track.parent).ok();
+        let (Some((body, t)), Some((_, pt))) = (alive, parent_alive) else {
+            tracks.remove(&e);
+            if track.captured {
+                if alive.is_some() {
+                    commands.entity(e).remove::<Satellite>();
+                }
+                lost.send(SatelliteLost {
+                    satellite: e,
+                    parent: track.parent,
+                    reason: if alive.is_none() {
+                        SatelliteLossReason::Collided
+                    } else {
+                        SatelliteLossReason::Escaped
+                    },
+                });
+            }
+            continue;
+        };
+
+        let still_bound = orbits
+            .get(e)
+            .is_some_and(|o| o.attractor == track.parent && o.kind.is_bound());
+        if still_bound {
+            track.unbound_ticks = 0;
+        } else {
+            track.unbound_ticks += 1;
+            if !track.captured || track.unbound_ticks > spec.escape_grace {
+                tracks.remove(&e);
+                if track.captured {
+                    commands.entity(e).remove::<Satellite>();
+                    lost.send(SatelliteLost {
+                        satellite: e,
+                        parent: track.parent,
+                        reason: SatelliteLossReason::Escaped,
+                    });
+                }
+                continue;
+            }
+        }
+
+        let rel = t.translation.truncate() - pt.translation.truncate();
+        let angle = rel.y.atan2(rel.x);
+        let delta = (angle - track.angle + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+        track.angle = angle;
+        let before = (track.swept.abs() / TAU).floor();
+        track.swept += delta;
+        let revolutions = track.swept.abs() / TAU;
+
+        let rarity = body.class.rarity();
+        if !track.captured && revolutions >= spec.capture_orbits {
+            track.captured = true;
+            commands.entity(e).insert(Satellite {
+                parent: track.parent,
+                orbits: revolutions,
+            });
+            captured.send(SatelliteCaptured {
+                satellite: e,
+                parent: track.parent,
+            });
+            player.score += spec.capture_score * rarity;
+        } else if track.captured {
+            if revolutions.floor() > before {
+                player.score += spec.orbit_score * rarity;
+            }
+            if let Ok(mut s) = satellites.get_mut(e) {
+                s.orbits = revolutions;
+            }
+        }
+        tracks.insert(e, track);
+    }
+
+    player.moons = tracks
+        .values()
+        .filter(|t| t.captured && t.parent == player_entity)
+        .count() as u32;
+}
@@ -0,0 +1,524 @@
+//! Full simulation snapshots, saved to and loaded from disk.
+//!
+//! A [`Snapshot`] holds everything the next tick depends on: every body
+//! (position, velocity, acceleration, mass, class and its player, hazard,
+//! charge, gas, disk and moon-tracking state), the nebulae, the settings,
+//! mission progress, the sim clock, where each [`SimRng`] stream stands, and
+//! the bookkeeping behind them (next body id, tree extent, hazard timer). It is
+//! written either as pretty RON, for reading and hand-editing, or as
+//! MessagePack behind a short binary header; both record the format version
+//! they were written with, and [`decode`] upgrades older ones.
+//!
+//! Loading replaces the running world and recomputes the [`StateHash`] from
+//! the restored world, which must equal the hash stored at save time.
+//! Derived state (orbits, structures, regularized pairs) is rebuilt by its
+//! systems over the following ticks.
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::accretion::{AccretionStats, DiskParticle};
+use super::nebula::{self, Nebula};
+use super::orbits::Orbits;
+use super::quadtree::Quad;
+use super::regularization::RegularizedPairs;
+use super::rng::{SimRng, STREAMS};
+use super::satellites::{SatelliteTracks, SavedTrack};
+use super::sph::{self, GasParticle};
+use super::state_hash::{self, body_records, BodyRecord, StateHash};
+use super::structure::Structures;
+use super::{
+    external_fields_for, spawn_body, spawn_player_body, AppState, Body, BodyId, Charge, Class,
+    Hazard, HazardSpawnTimer, Mission, NextBodyId, Player, SimClock, SimSettings, SimStats,
+    TreeState,
+};
+
+/// Layout version written into every snapshot. Bump it (and add a step to
+/// `MIGRATIONS`) whenever a change would make old snapshots load differently.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Leads every binary snapshot, followed by the version as a little-endian u32.
+const MAGIC: &[u8; 8] = b"S2SNAP\0\0";
+
+/// Fix-ups applied to a decoded snapshot written with version `.0`, bringing it
+/// to `.0 + 1`. Fields added since an old version take their serde defaults.
+const MIGRATIONS: &[(u32, fn(&mut Snapshot))] = &[
+    // Version 2 only changed the binary encoding; RON carries over as it is.
+    (1, |_| {}),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SnapshotFormat {
+    Ron,
+    Binary,
+}
+
+impl SnapshotFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            SnapshotFormat::Ron => "ron",
+            SnapshotFormat::Binary => "bin",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedBody {
+    pub id: BodyId,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub acc: Vec2,
+    pub mass: f32,
+    pub class: Class,
+    #[serde(default)]
+    pub player: Option<Player>,
+    #[serde(default)]
+    pub hazard: bool,
+    #[serde(default)]
+    pub charge: Option<f32>,
+    #[serde(default)]
+    pub gas: Option<GasParticle>,
+    /// The black hole whose accretion disk this body is part of.
+    #[serde(default)]
+    pub disk_host: Option<BodyId>,
+    /// Moon tracking around the player's family.
+    #[serde(default)]
+    pub track: Option<SavedTrack>,
+}
+
+impl SavedBody {
+    fn body(&self) -> Body {
+        Body {
+            mass: self.mass,
+            vel: self.vel,
+            acc: self.acc,
+            class: self.class,
+        }
+    }
+
+    fn record(&self) -> BodyRecord {
+        BodyRecord {
+            id: self.id,
+            pos: self.pos,
+            vel: self.vel,
+            mass: self.mass,
+            class: self.class,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedNebula {
+    pub nebula: Nebula,
+    /// Sprite colour as sRGBA.
+    pub tint: [f32; 4],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// State hash of the saved tick; loading must reproduce it.
+    pub hash: u64,
+    pub clock: SimClock,
+    pub settings: SimSettings,
+    pub mission: Mission,
+    pub rng_seed: u64,
+    /// Words drawn so far from each [`SimRng`] stream.
+    pub rng_positions: [u64; STREAMS],
+    pub next_body_id: u64,
+    /// Half-size of the quadtree root, which only ever grows during a run.
+    pub tree_extent: f32,
+    /// Sim seconds since the last hazard.
+    pub hazard_elapsed: f32,
+    pub accretion: AccretionStats,
+    pub nebulae: Vec<SavedNebula>,
+    /// In the world's iteration order, which `restore` spawns them back in:
+    /// force and collision sums run in that order, so a loaded run only keeps
+    /// matching the original if it is preserved.
+    pub bodies: Vec<SavedBody>,
+}
+
+impl Snapshot {
+    fn word_positions(&self) -> [u128; STREAMS] {
+        self.rng_positions.map(u128::from)
+    }
+
+    /// The state hash of the snapshot's contents.
+    fn compute_hash(&self) -> u64 {
+        let mut records: Vec<BodyRecord> = self.bodies.iter().map(SavedBody::record).collect();
+        records.sort_by_key(|r| r.id);
+        state_hash::combine(
+            self.clock.tick,
+            &records,
+            self.word_positions(),
+            state_hash::settings_hash(&self.settings),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    Encode(String),
+    Decode(String),
+    UnsupportedVersion(u32),
+    /// The snapshot loaded, but the restored state hashes differently than when saved.
+    HashMismatch {
+        saved: u64,
+        restored: u64,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{e}"),
+            SnapshotError::Encode(e) => write!(f, "could not encode snapshot: {e}"),
+            SnapshotError::Decode(e) => write!(f, "could not read snapshot: {e}"),
+            SnapshotError::UnsupportedVersion(v) => write!(
+                f,
+                "snapshot version {v} is not supported (this build reads up to {SNAPSHOT_VERSION})"
+            ),
+            SnapshotError::HashMismatch { saved, restored } => write!(
+                f,
+                "restored state hashes to {restored:016x}, saved as {saved:016x}"
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+/// Serializes `snapshot` in `format`.
+pub fn encode(snapshot: &Snapshot, format: SnapshotFormat) -> Result<Vec<u8>, SnapshotError> {
+    match format {
+        SnapshotFormat::Ron => {
+            // One line per body and nebula keeps large snapshots diffable.
+            let config = PrettyConfig::new().depth_limit(2);
+            let text = ron::ser::to_string_pretty(snapshot, config)
+                .map_err(|e| SnapshotError::Encode(e.to_string()))?;
+            Ok(format!("// solar2-rs snapshot\n{text}\n").into_bytes())
+        }
+        SnapshotFormat::Binary => {
+            let mut out = MAGIC.to_vec();
+            out.extend_from_slice(&snapshot.version.to_le_bytes());
+            rmp_serde::encode::write_named(&mut out, snapshot)
+                .map_err(|e| SnapshotError::Encode(e.to_string()))?;
+            Ok(out)
+        }
+    }
+}
+
+/// Parses a snapshot in either format and upgrades it to [`SNAPSHOT_VERSION`].
+///
+/// Both formats name every field, so older snapshots decode straight into the
+/// current layout before `MIGRATIONS` run. Version 1 binary snapshots were
+/// bincode, which carries no field names, and cannot be read.
+pub fn decode(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+    let mut snapshot: Snapshot = match bytes.strip_prefix(MAGIC.as_slice()) {
+        Some(rest) => {
+            let (version, payload) = rest
+                .split_first_chunk::<4>()
+                .ok_or_else(|| SnapshotError::Decode("truncated header".into()))?;
+            let version = u32::from_le_bytes(*version);
+            if version == 1 {
+                return Err(SnapshotError::Decode(
+                    "version 1 binary snapshots are bincode and cannot be migrated".into(),
+                ));
+            }
+            if version > SNAPSHOT_VERSION {
+                return Err(SnapshotError::UnsupportedVersion(version));
+            }
+            rmp_serde::from_slice(payload).map_err(|e| SnapshotError::Decode(e.to_string()))?
+        }
+        None => {
+            let text =
+                std::str::from_utf8(bytes).map_err(|e| SnapshotError::Decode(e.to_string()))?;
+            ron::from_str(text).map_err(|e| SnapshotError::Decode(e.to_string()))?
+        }
+    };
+    if snapshot.version == 0 || snapshot.version > SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(snapshot.version));
+    }
+    for (from, step) in MIGRATIONS {
+        if snapshot.version == *from {
+            step(&mut snapshot);
+            snapshot.version = from + 1;
+        }
+    }
+    Ok(snapshot)
+}
+
+pub fn write_file(
+    path: &Path,
+    snapshot: &Snapshot,
+    format: SnapshotFormat,
+) -> Result<(), SnapshotError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, encode(snapshot, format)?)?;
+    Ok(())
+}
+
+pub fn read_file(path: &Path) -> Result<Snapshot, SnapshotError> {
+    decode(&std::fs::read(path)?)
+}
+
+/// Records the current world. Bodies spawned this tick get their [`BodyId`] at
+/// the end of it and are only included once they have one.
+pub fn capture(world: &mut World) -> Snapshot {
+    let ids: HashMap<Entity, BodyId> = world
+        .query::<(Entity, &BodyId)>()
+        .iter(world)
+        .map(|(e, id)| (e, *id))
+        .collect();
+    let mut q = world.query::<(
+        Entity,
+        &BodyId,
+        &Body,
+        &Transform,
+        Option<&Player>,
+        Has<Hazard>,
+        Option<&Charge>,
+        Option<&GasParticle>,
+        Option<&DiskParticle>,
+    )>();
+    let tracks = world.resource::<SatelliteTracks>();
+    let bodies: Vec<SavedBody> = q
+        .iter(world)
+        .map(
+            |(e, id, b, t, player, hazard, charge, gas, disk)| SavedBody {
+                id: *id,
+                pos: t.translation.truncate(),
+                vel: b.vel,
+                acc: b.acc,
+                mass: b.mass,
+                class: b.class,
+                player: player.copied(),
+                hazard,
+                charge: charge.map(|c| c.0),
+                gas: gas.copied(),
+                disk_host: disk.and_then(|d| ids.get(&d.host).copied()),
+                track: tracks.save(e, &ids),
+            },
+        )
+        .collect();
+    // Drag sums over nebulae in this order too.
+    let nebulae = world
+        .query::<(&Nebula, &Sprite)>()
+        .iter(world)
+        .map(|(nebula, sprite)| SavedNebula {
+            nebula: *nebula,
+            tint: sprite.color.to_srgba().to_f32_array(),
+        })
+        .collect();
+
+    let rng = world.resource::<SimRng>();
+    let mut snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        hash: 0,
+        clock: *world.resource::<SimClock>(),
+        settings: world.resource::<SimSettings>().clone(),
+        mission: world.resource::<Mission>().clone(),
+        rng_seed: rng.seed(),
+        rng_positions: rng.word_positions().map(|p| p as u64),
+        next_body_id: world.resource::<NextBodyId>().0,
+        tree_extent: world.resource::<TreeState>().bounds.half_size,
+        hazard_elapsed: world
+            .resource::<HazardSpawnTimer>()
+            .0
+            .elapsed()
+            .as_secs_f32(),
+        accretion: *world.resource::<AccretionStats>(),
+        nebulae,
+        bodies,
+    };
+    snapshot.hash = snapshot.compute_hash();
+    snapshot
+}
+
+/// Replaces the world's bodies, nebulae and simulation resources with
+/// `snapshot`'s, then hashes the restored world. The state is kept even when
+/// that hash differs from the saved one, which is reported as
+/// [`SnapshotError::HashMismatch`].
+pub fn restore(world: &mut World, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+    let doomed: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Body>, With<Nebula>)>>()
+        .iter(world)
+        .collect();
+    for e in doomed {
+        world.entity_mut(e).despawn_recursive();
+    }
+
+    let settings = snapshot.settings.clone();
+    let mut commands = world.commands();
+    for saved in &snapshot.nebulae {
+        let [r, g, b, a] = saved.tint;
+        nebula::spawn_nebula(&mut commands, saved.nebula, Color::srgba(r, g, b, a));
+    }
+    let mut entities: HashMap<BodyId, Entity> = HashMap::with_capacity(snapshot.bodies.len());
+    for saved in &snapshot.bodies {
+        let e = if let Some(player) = saved.player {
+            spawn_player_body(&mut commands, saved.pos, saved.body(), player)
+        } else if let Some(gas) = saved.gas {
+            sph::spawn_gas_particle(&mut commands, saved.pos, saved.body(), gas)
+        } else {
+            spawn_body(&mut commands, &settings, saved.pos, saved.vel, saved.mass)
+        };
+        let mut entity = commands.entity(e);
+        entity.insert((saved.body(), saved.id));
+        if saved.hazard {
+            entity.insert(Hazard);
+        }
+        if let Some(q) = saved.charge {
+            entity.insert(Charge(q));
+        }
+        entities.insert(saved.id, e);
+    }
+    let mut tracks = SatelliteTracks::default();
+    for saved in &snapshot.bodies {
+        let e = entities[&saved.id];
+        let host = saved.disk_host.and_then(|id| entities.get(&id));
+        if let Some(&host) = host {
+            commands.entity(e).insert(DiskParticle { host });
+        }
+        let Some(track) = &saved.track else {
+            continue;
+        };
+        let Some(&parent) = entities.get(&track.parent) else {
+            continue;
+        };
+        if let Some(satellite) = tracks.restore(e, parent, track) {
+            commands.entity(e).insert(satellite);
+        }
+    }
+    world.flush();
+
+    let mut hazard_timer = world.resource_mut::<HazardSpawnTimer>();
+    hazard_timer
+        .0
+        .set_elapsed(Duration::from_secs_f32(snapshot.hazard_elapsed));
+    world.insert_resource(SimStats(snapshot.bodies.len()));
+    world.insert_resource(external_fields_for(&settings));
+    world.insert_resource(settings);
+    world.insert_resource(snapshot.clock);
+    world.insert_resource(snapshot.mission.clone());
+    world.insert_resource(SimRng::restore(
+        snapshot.rng_seed,
+        snapshot.word_positions(),
+    ));
+    world.insert_resource(NextBodyId(snapshot.next_body_id));
+    world.insert_resource(TreeState {
+        root: None,
+        bounds: Quad::new(Vec2::ZERO, snapshot.tree_extent),
+    });
+    world.insert_resource(snapshot.accretion);
+    world.insert_resource(Orbits::default());
+    world.insert_resource(Structures::default());
+    world.insert_resource(tracks);
+    world.insert_resource(RegularizedPairs::default());
+    // Back into play after a death; the replay viewer stays where it is.
+    let over = world
+        .get_resource::<State<AppState>>()
+        .is_some_and(|state| *state.get() == AppState::GameOver);
+    if over {
+        world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Playing);
+    }
+
+    let settings_hash = state_hash::settings_hash(&snapshot.settings);
+    let restored = state_hash::combine(
+        world.resource::<SimClock>().tick,
+        &body_records(world),
+        world.resource::<SimRng>().word_positions(),
+        settings_hash,
+    );
+    world.insert_resource(StateHash {
+        tick: snapshot.clock.tick,
+        value: restored,
+        settings: settings_hash,
+    });
+    if restored != snapshot.hash {
+        return Err(SnapshotError::HashMismatch {
+            saved: snapshot.hash,
+            restored,
+        });
+    }
+    Ok(())
+}
+
+/// Writes a snapshot of the current tick to `path`.
+#[derive(Event, Clone, Debug)]
+pub struct SaveSnapshot {
+    pub path: PathBuf,
+    pub format: SnapshotFormat,
+}
+
+/// Replaces the running simulation with the snapshot at `path`.
+#[derive(Event, Clone, Debug)]
+pub struct LoadSnapshot {
+    pub path: PathBuf,
+}
+
+/// Outcome of the latest save or load, for the HUD.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct SnapshotStatus {
+    pub message: Option<String>,
+}
+
+pub fn quicksave_path(format: SnapshotFormat) -> PathBuf {
+    Path::new("saves").join(format!("quicksave.{}", format.extension()))
+}
+
+/// Handles [`SaveSnapshot`] requests once the tick's hash is known.
+pub(super) fn save_snapshots(world: &mut World) {
+    let requests: Vec<SaveSnapshot> = world
+        .resource_mut::<Events<SaveSnapshot>>()
+        .drain()
+        .collect();
+    if requests.is_empty() {
+        return;
+    }
+    let snapshot = capture(world);
+    for request in requests {
+        let message = match write_file(&request.path, &snapshot, request.format) {
+            Ok(()) => format!(
+                "Saved tick {} to {}",
+                snapshot.clock.tick,
+                request.path.display()
+            ),
+            Err(e) => format!("Save to {} failed: {e}", request.path.display()),
+        };
+        world.resource_mut::<SnapshotStatus>().message = Some(message);
+    }
+}
+
+/// Handles the latest [`LoadSnapshot`] request, between ticks.
+pub(super) fn load_snapshots(world: &mut World) {
+    let Some(request) = world.resource_mut::<Events<LoadSnapshot>>().drain().last() else {
+        return;
+    };
+    let path = request.path.display();
+    let message = match read_file(&request.path) {
+        Ok(snapshot) => match restore(world, &snapshot) {
+            Ok(()) => format!(
+                "Loaded tick {} from {path} (hash {:016x})",
+                snapshot.clock.tick, snapshot.hash
+            ),
+            Err(e) => format!("Loaded {path}, but {e}"),
+        },
+        Err(e) => format!("Could not load {path}: {e}"),
+    };
+    world.resource_mut::<SnapshotStatus>().message = Some(message);
+}
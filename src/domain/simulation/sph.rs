@@ -0,0 +1,412 @@
+//! Smoothed-particle hydrodynamics for the gas that new stars condense out of.
+//!
+//! Gas particles are ordinary [`Body`] entities tagged with [`GasParticle`], so
+//! they drift, kick and feel gravity through the quadtree like everything else.
+//! `apply_sph` estimates density with a 2D cubic-spline kernel, adds pressure and
+//! artificial-viscosity accelerations from neighbours found in a [`SpatialHash`]
+//! sized to the kernel, and evolves internal energy (compressional heating plus
+//! cooling towards a floor). `form_stars` turns converging gas whose Jeans length
+//! has shrunk below the kernel into a single new star.
+
+use bevy::prelude::*;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use super::initial_conditions::{gaussian, BodyInit};
+use super::nebula::{Nebula, NebulaTexture};
+use super::{spawn_body, Body, Class, SimSettings, SimStats, SpatialHash};
+
+/// Gas physics and the gas placed by the scenario.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SphSettings {
+    pub enabled: bool,
+    /// Gas particles scattered through the nebulae on spawn.
+    pub count: usize,
+    pub particle_mass: f32,
+    /// Specific internal energy the gas starts with.
+    pub initial_energy: f32,
+    /// Cooling never takes the gas below this specific internal energy.
+    pub energy_floor: f32,
+    /// e-folding time (sim seconds) of radiative cooling towards `energy_floor`.
+    pub cooling_time: f32,
+    /// Adiabatic index of the ideal-gas equation of state P = (γ − 1)ρu.
+    pub gamma: f32,
+    /// Smoothing length in units of the local interparticle spacing √(m/ρ).
+    pub eta: f32,
+    pub smoothing_range: (f32, f32),
+    /// Monaghan artificial viscosity (α, β).
+    pub viscosity: (f32, f32),
+    pub star_formation: bool,
+}
+
+impl Default for SphSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            count: 0,
+            particle_mass: 1000.0,
+            initial_energy: 9000.0, // sound speed ≈ 100
+            energy_floor: 1100.0,   // sound speed ≈ 35
+            cooling_time: 4.0,
+            gamma: 5.0 / 3.0,
+            eta: 1.3,
+            smoothing_range: (10.0, 200.0),
+            viscosity: (1.0, 2.0),
+            star_formation: true,
+        }
+    }
+}
+
+/// Hydrodynamic state of one gas particle, refreshed every SPH pass.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GasParticle {
+    pub density: f32,
+    pub pressure: f32,
+    /// Specific internal energy u.
+    pub energy: f32,
+    /// Smoothing length h; the kernel reaches out to 2h.
+    pub smoothing: f32,
+    /// Velocity divergence (negative while the gas converges).
+    pub div_v: f32,
+}
+
+impl GasParticle {
+    pub fn sound_speed(&self, gamma: f32) -> f32 {
+        (gamma * (gamma - 1.0) * self.energy).max(0.0).sqrt()
+    }
+}
+
+/// 2D cubic-spline kernel W(r, h) with support 2h.
+fn kernel(r: f32, h: f32) -> f32 {
+    let q = r / h;
+    let sigma = 10.0 / (7.0 * PI * h * h);
+    if q < 1.0 {
+        sigma * (1.0 - 1.5 * q * q + 0.75 * q * q * q)
+    } else if q < 2.0 {
+        sigma * 0.25 * (2.0 - q).powi(3)
+    } else {
+        0.0
+    }
+}
+
+/// dW/dr of [`kernel`].
+fn kernel_slope(r: f32, h: f32) -> f32 {
+    let q = r / h;
+    let sigma = 10.0 / (7.0 * PI * h * h);
+    if q < 1.0 {
+        sigma / h * (-3.0 * q + 2.25 * q * q)
+    } else if q < 2.0 {
+        -sigma / h * 0.75 * (2.0 - q) * (2.0 - q)
+    } else {
+        0.0
+    }
+}
+
+/// Scatters `settings.sph.count` gas particles through `clouds`, weighted by cloud
+/// area and following each cloud's Gaussian profile. Returns what was spawned.
+pub(super) fn spawn_gas<R: RngCore + ?Sized>(
+    commands: &mut Commands,
+    settings: &SimSettings,
+    clouds: &[Nebula],
+    rng: &mut R,
+) -> Vec<BodyInit> {
+    let spec = &settings.sph;
+    let total_area: f32 = clouds.iter().map(|n| n.radius * n.radius).sum();
+    if spec.count == 0 || total_area <= 0.0 {
+        return Vec::new();
+    }
+    let mass = spec.particle_mass;
+    let mut spawned = Vec::with_capacity(spec.count);
+    for _ in 0..spec.count {
+        let mut pick = rng.gen::<f32>() * total_area;
+        let cloud = clouds
+            .iter()
+            .find(|n| {
+                pick -= n.radius * n.radius;
+                pick <= 0.0
+            })
+            .unwrap_or(&clouds[clouds.len() - 1]);
+        let offset = (Vec2::new(gaussian(rng), gaussian(rng)) * 0.5 * cloud.radius)
+            .clamp_length_max(1.5 * cloud.radius);
+        let pos = cloud.centre + offset;
+        let vel = cloud.gas_velocity(pos);
+        spawn_gas_particle(
+            commands,
+            pos,
+            Body {
+                mass,
+                vel,
+                acc: Vec2::ZERO,
+                class: Class::from_mass(mass),
+            },
+            GasParticle {
+                density: 0.0,
+                pressure: 0.0,
+                energy: spec.initial_energy,
+                smoothing: spec.smoothing_range.1,
+                div_v: 0.0,
+            },
+        );
+        spawned.push(BodyInit { pos, vel, mass });
+    }
+    spawned
+}
+
+/// Spawns a gas particle; `style_new_gas` gives it its sprite later.
+pub(super) fn spawn_gas_particle(
+    commands: &mut Commands,
+    pos: Vec2,
+    body: Body,
+    gas: GasParticle,
+) -> Entity {
+    commands
+        .spawn((
+            body,
+            gas,
+            SpriteBundle {
+                transform: Transform::from_translation(pos.extend(-1.0)),
+                ..default()
+            },
+        ))
+        .id()
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    pos: Vec2,
+    vel: Vec2,
+    mass: f32,
+    h: f32,
+    density: f32,
+    pressure: f32,
+    sound: f32,
+}
+
+/// Density, pressure and viscous forces, plus the energy equation with cooling.
+pub(super) fn apply_sph(
+    settings: Res<SimSettings>,
+    mut hash: ResMut<SpatialHash>,
+    mut q: Query<(Entity, &mut Body, &mut GasParticle, &Transform)>,
+) {
+    let sph = settings.sph;
+    if !settings.running || !sph.enabled || q.is_empty() {
+        return;
+    }
+    let dt = settings.dt * settings.time_scale;
+    let (h_min, h_max) = sph.smoothing_range;
+
+    let mut parts: Vec<(Entity, Sample, f32)> = q
+        .iter()
+        .map(|(e, b, gas, t)| {
+            let h = if gas.density > 0.0 {
+                (sph.eta * (b.mass / gas.density).sqrt()).clamp(h_min, h_max)
+            } else {
+                h_max
+            };
+            let sample = Sample {
+                pos: t.translation.truncate(),
+                vel: b.vel,
+                mass: b.mass,
+                h,
+                density: 0.0,
+                pressure: 0.0,
+                sound: gas.sound_speed(sph.gamma),
+            };
+            (e, sample, gas.energy)
+        })
+        .collect();
+    let index: HashMap<Entity, usize> = parts
+        .iter()
+        .enumerate()
+        .map(|(i, (e, _, _))| (*e, i))
+        .collect();
+
+    let reach = 2.0 * parts.iter().map(|(_, s, _)| s.h).fold(h_min, f32::max);
+    hash.reset(reach);
+    for (e, s, _) in &parts {
+        hash.insert(*e, s.pos);
+    }
+    let neighbours = |i: usize, parts: &[(Entity, Sample, f32)]| {
+        let mut out = Vec::new();
+        hash.for_each_near(parts[i].1.pos, reach, |e| {
+            if let Some(&j) = index.get(&e) {
+                out.push(j);
+            }
+        });
+        out
+    };
+
+    // Density and velocity divergence (gather with each particle's own h).
+    let mut div_v = vec![0.0; parts.len()];
+    for i in 0..parts.len() {
+        let si = parts[i].1;
+        let mut rho = 0.0;
+        let mut div = 0.0;
+        for j in neighbours(i, &parts) {
+            let sj = parts[j].1;
+            let d = si.pos - sj.pos;
+            let r = d.length();
+            rho += sj.mass * kernel(r, si.h);
+            if r > 0.0 {
+                div += sj.mass * (sj.vel - si.vel).dot(d / r) * kernel_slope(r, si.h);
+            }
+        }
+        let u = parts[i].2;
+        let s = &mut parts[i].1;
+        s.density = rho.max(1e-6);
+        s.pressure = (sph.gamma - 1.0) * s.density * u;
+        div_v[i] = div / s.density;
+    }
+
+    // Symmetrised pressure + viscosity forces and the matching heating term.
+    let (alpha, beta) = sph.viscosity;
+    let mut acc = vec![Vec2::ZERO; parts.len()];
+    let mut du = vec![0.0; parts.len()];
+    for i in 0..parts.len() {
+        let si = parts[i].1;
+        for j in neighbours(i, &parts) {
+            if i == j {
+                continue;
+            }
+            let sj = parts[j].1;
+            let d = si.pos - sj.pos;
+            let r = d.length();
+            let h = 0.5 * (si.h + sj.h);
+            if r <= 0.0 || r >= 2.0 * h {
+                continue;
+            }
+            let grad = d / r * kernel_slope(r, h);
+            let v = si.vel - sj.vel;
+            let approach = v.dot(d);
+            let visc = if approach < 0.0 {
+                let mu = h * approach / (r * r + 0.01 * h * h);
+                let c = 0.5 * (si.sound + sj.sound);
+                let rho = 0.5 * (si.density + sj.density);
+                (-alpha * c * mu + beta * mu * mu) / rho
+            } else {
+                0.0
+            };
+            let term = si.pressure / (si.density * si.density)
+                + sj.pressure / (sj.density * sj.density)
+                + visc;
+            acc[i] -= sj.mass * term * grad;
+            du[i] += 0.5 * sj.mass * term * v.dot(grad);
+        }
+    }
+
+    let cooling = if sph.cooling_time > 0.0 {
+        (-dt / sph.cooling_time).exp()
+    } else {
+        0.0
+    };
+    for (e, mut b, mut gas, _) in &mut q {
+        let Some(&i) = index.get(&e) else {
+            continue;
+        };
+        let s = parts[i].1;
+        b.acc += acc[i];
+        let heated = (parts[i].2 + du[i] * dt).max(sph.energy_floor);
+        gas.energy = sph.energy_floor + (heated - sph.energy_floor) * cooling;
+        gas.density = s.density;
+        gas.pressure = s.pressure;
+        gas.smoothing = s.h;
+        gas.div_v = div_v[i];
+    }
+}
+
+/// Collapses Jeans-unstable gas into stars.
+///
+/// In a thin sheet the Jeans length is c_s²/(Gρ); once it drops below the
+/// kernel support 2h while the gas is converging, pressure can no longer hold
+/// the kernel up and all gas within 2h becomes one body at its centre of mass,
+/// provided there is enough of it to make a star.
+pub(super) fn form_stars(
+    mut commands: Commands,
+    settings: Res<SimSettings>,
+    mut stats: ResMut<SimStats>,
+    q: Query<(Entity, &Body, &GasParticle, &Transform)>,
+) {
+    let sph = settings.sph;
+    if !settings.running || !sph.enabled || !sph.star_formation {
+        return;
+    }
+    let mut gas: Vec<(Entity, Vec2, Vec2, f32, GasParticle)> = q
+        .iter()
+        .map(|(e, b, g, t)| (e, t.translation.truncate(), b.vel, b.mass, *g))
+        .collect();
+    // Densest first, with the entity as a tie-break so the outcome is order-independent.
+    gas.sort_by(|a, b| b.4.density.total_cmp(&a.4.density).then(a.0.cmp(&b.0)));
+
+    let mut consumed = vec![false; gas.len()];
+    for i in 0..gas.len() {
+        let (_, pos, _, _, g) = gas[i];
+        if consumed[i] || g.density <= 0.0 || g.div_v >= 0.0 {
+            continue;
+        }
+        let c = g.sound_speed(sph.gamma);
+        let jeans_length = c * c / (settings.g * g.density);
+        if jeans_length >= 2.0 * g.smoothing {
+            continue;
+        }
+
+        let reach2 = (2.0 * g.smoothing).powi(2);
+        let clump: Vec<usize> = (0..gas.len())
+            .filter(|&j| !consumed[j] && (gas[j].1 - pos).length_squared() <= reach2)
+            .collect();
+        // Too little gas for a star would only make a planet; leave it to gather.
+        let total: f32 = clump.iter().map(|&j| gas[j].3).sum();
+        if matches!(Class::from_mass(total), Class::Asteroid | Class::Planet) {
+            continue;
+        }
+        let (mut mass, mut moment, mut momentum) = (0.0, Vec2::ZERO, Vec2::ZERO);
+        for j in clump {
+            let (e, p, v, m, _) = gas[j];
+            consumed[j] = true;
+            mass += m;
+            moment += p * m;
+            momentum += v * m;
+            commands.entity(e).despawn_recursive();
+            stats.0 = stats.0.saturating_sub(1);
+        }
+        spawn_body(
+            &mut commands,
+            &settings,
+            moment / mass,
+            momentum / mass,
+            mass,
+        );
+        stats.0 += 1;
+    }
+}
+
+/// Gives new gas particles the soft cloud sprite.
+pub(super) fn style_new_gas(
+    texture: Option<Res<NebulaTexture>>,
+    mut q: Query<&mut Handle<Image>, Added<GasParticle>>,
+) {
+    let Some(texture) = texture else {
+        return;
+    };
+    for mut image in &mut q {
+        *image = texture.0.clone();
+    }
+}
+
+/// Sizes gas sprites to their kernel and tints them from cold blue to hot orange.
+pub(super) fn update_gas_render(
+    settings: Res<SimSettings>,
+    mut q: Query<(&GasParticle, &mut Sprite)>,
+) {
+    let sph = &settings.sph;
+    let span = (sph.initial_energy - sph.energy_floor).max(1.0);
+    let cold = LinearRgba::rgb(0.35, 0.5, 1.0);
+    let hot = LinearRgba::rgb(1.0, 0.55, 0.25);
+    for (gas, mut sprite) in &mut q {
+        let t = ((gas.energy - sph.energy_floor) / span).clamp(0.0, 2.0) * 0.5;
+        sprite.color = Color::from(cold.mix(&hot, t)).with_alpha(0.3);
+        sprite.custom_size = Some(Vec2::splat(2.0 * gas.smoothing));
+    }
+}
@@ -0,0 +1,158 @@
+//! Canonical hash of the simulation state, recomputed every tick.
+//!
+//! `hash_state` folds the tick number, every body (in [`BodyId`] order: id,
+//! position, velocity, mass and class, as raw bits), the position of each
+//! [`SimRng`] stream and a digest of the physics settings into one FNV-1a
+//! value. Two runs that agree on it tick for tick are bit-identical; the
+//! headless determinism checker compares these hashes and, at the first
+//! mismatch, the [`BodyRecord`]s behind them.
+
+use bevy::prelude::*;
+
+use super::rng::{SimRng, STREAMS};
+use super::{Body, BodyId, Class, SimClock, SimSettings};
+
+/// 64-bit FNV-1a.
+pub struct Fnv(u64);
+
+impl Fnv {
+    pub fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    pub fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+    pub fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+    pub fn f32(&mut self, v: f32) {
+        self.u32(v.to_bits());
+    }
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The hashed part of one body.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BodyRecord {
+    pub id: BodyId,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub mass: f32,
+    pub class: Class,
+}
+
+impl BodyRecord {
+    fn write(&self, h: &mut Fnv) {
+        h.u64(self.id.0);
+        for v in [self.pos.x, self.pos.y, self.vel.x, self.vel.y, self.mass] {
+            h.f32(v);
+        }
+        h.u32(self.class as u32);
+    }
+
+    /// Bitwise comparison; unlike `==` it tells -0.0 from 0.0 and matches NaNs.
+    pub fn same_bits(&self, other: &BodyRecord) -> bool {
+        let mut a = Fnv::new();
+        let mut b = Fnv::new();
+        self.write(&mut a);
+        other.write(&mut b);
+        a.finish() == b.finish()
+    }
+}
+
+/// Hash of the latest tick.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct StateHash {
+    pub tick: u64,
+    pub value: u64,
+    /// Digest of the physics-relevant settings, refreshed when they change.
+    pub settings: u64,
+}
+
+/// Every numbered body, sorted by id.
+pub fn body_records(world: &mut World) -> Vec<BodyRecord> {
+    let mut q = world.query::<(&BodyId, &Body, &Transform)>();
+    collect(q.iter(world))
+}
+
+fn collect<'a>(it: impl Iterator<Item = (&'a BodyId, &'a Body, &'a Transform)>) -> Vec<BodyRecord> {
+    let mut records: Vec<BodyRecord> = it
+        .map(|(id, b, t)| BodyRecord {
+            id: *id,
+            pos: t.translation.truncate(),
+            vel: b.vel,
+            mass: b.mass,
+            class: b.class,
+        })
+        .collect();
+    records.sort_by_key(|r| r.id);
+    records
+}
+
+pub(super) fn settings_hash(settings: &SimSettings) -> u64 {
+    // Display-only switches must not make two otherwise identical runs differ.
+    let physics = SimSettings {
+        show_help: false,
+        show_diagnostics: false,
+        follow_player: false,
+        trails_enabled: false,
+        trail_lifespan: 0.0,
+        color_palette: default(),
+        ..settings.clone()
+    };
+    let mut h = Fnv::new();
+    h.bytes(format!("{physics:?}").as_bytes());
+    h.finish()
+}
+
+/// Folds one tick's parts into its hash; `records` must be sorted by id.
+pub(super) fn combine(
+    tick: u64,
+    records: &[BodyRecord],
+    word_positions: [u128; STREAMS],
+    settings: u64,
+) -> u64 {
+    let mut h = Fnv::new();
+    h.u64(tick);
+    for record in records {
+        record.write(&mut h);
+    }
+    for pos in word_positions {
+        h.bytes(&pos.to_le_bytes());
+    }
+    h.u64(settings);
+    h.finish()
+}
+
+pub(super) fn hash_state(
+    clock: Res<SimClock>,
+    settings: Res<SimSettings>,
+    rng: Res<SimRng>,
+    mut state: ResMut<StateHash>,
+    q: Query<(&BodyId, &Body, &Transform)>,
+) {
+    if settings.is_changed() || state.settings == 0 {
+        state.settings = settings_hash(&settings);
+    }
+    state.tick = clock.tick;
+    state.value = combine(
+        clock.tick,
+        &collect(q.iter()),
+        rng.word_positions(),
+        state.settings,
+    );
+}
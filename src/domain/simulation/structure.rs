@@ -0,0 +1,352 @@
+//! Emergent-structure detection: friends-of-friends groups and bound subsystems.
+//!
+//! Every `interval` ticks `detect_structure` links bodies closer than a linking
+//! length (a fraction of the mean interparticle spacing) into [`Group`]s using a
+//! [`SpatialHash`] with one cell per linking length, and walks the [`Orbits`]
+//! hierarchy to collect [`Subsystem`]s (moons around planets, planets around
+//! stars). Group ids survive from one pass to the next by majority vote of
+//! their members, so the HUD, missions and scoring can follow a clump as it
+//! grows, and see when several groups merge into one.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::orbits::Orbits;
+use super::sph::GasParticle;
+use super::{Body, Class, SimClock, SimSettings, SpatialHash};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StructureSettings {
+    pub enabled: bool,
+    /// Linking length in units of the mean interparticle spacing.
+    pub linking_factor: f32,
+    /// Smaller friends-of-friends clumps are ignored.
+    pub min_members: usize,
+    /// Sim ticks between passes.
+    pub interval: u64,
+}
+
+impl Default for StructureSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            linking_factor: 0.2,
+            min_members: 8,
+            interval: 30,
+        }
+    }
+}
+
+/// Rough shape of a group, judged from how its members sit around the centre of mass.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GroupShape {
+    Clump,
+    /// Members lie at nearly the same distance from a hollow centre.
+    Ring,
+}
+
+/// A friends-of-friends group.
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub id: u32,
+    pub members: Vec<Entity>,
+    pub mass: f32,
+    pub com: Vec2,
+    pub vel: Vec2,
+    /// Mass-weighted mean distance of members from the centre of mass.
+    pub mean_radius: f32,
+    pub shape: GroupShape,
+    /// Ids of earlier groups that mostly ended up in this one during the last pass.
+    pub merged_from: Vec<u32>,
+}
+
+/// A primary and everything bound to it, directly or through its own satellites.
+#[derive(Clone, Debug)]
+pub struct Subsystem {
+    pub id: u32,
+    pub primary: Entity,
+    pub primary_class: Class,
+    /// Bodies on bound orbits around `primary` itself.
+    pub satellites: Vec<Entity>,
+    /// Primary plus all bound descendants.
+    pub mass: f32,
+    pub com: Vec2,
+    pub vel: Vec2,
+}
+
+/// Latest structure pass.
+#[derive(Resource, Default)]
+pub struct Structures {
+    pub groups: Vec<Group>,
+    pub subsystems: Vec<Subsystem>,
+    /// `SimClock::tick` of the pass that produced these lists.
+    pub tick: u64,
+    membership: HashMap<Entity, u32>,
+    subsystem_ids: HashMap<Entity, u32>,
+    next_id: u32,
+}
+
+impl Structures {
+    pub fn group(&self, id: u32) -> Option<&Group> {
+        self.groups.iter().find(|g| g.id == id)
+    }
+    /// The group `e` belonged to at the last pass.
+    pub fn group_of(&self, e: Entity) -> Option<&Group> {
+        self.membership.get(&e).and_then(|id| self.group(*id))
+    }
+    pub fn subsystem_of(&self, primary: Entity) -> Option<&Subsystem> {
+        self.subsystems.iter().find(|s| s.primary == primary)
+    }
+    pub fn largest_group(&self) -> Option<&Group> {
+        self.groups.iter().max_by_key(|g| g.members.len())
+    }
+    pub fn rings(&self) -> impl Iterator<Item = &Group> {
+        self.groups.iter().filter(|g| g.shape == GroupShape::Ring)
+    }
+    fn fresh_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[derive(Clone, Copy)]
+struct Point {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    mass: f32,
+}
+
+pub(super) fn detect_structure(
+    settings: Res<SimSettings>,
+    clock: Res<SimClock>,
+    orbits: Res<Orbits>,
+    mut structures: ResMut<Structures>,
+    mut hash: ResMut<SpatialHash>,
+    q: Query<(Entity, &Body, &Transform), Without<GasParticle>>,
+) {
+    let spec = settings.structure;
+    if !spec.enabled || clock.tick < structures.tick + spec.interval.max(1) {
+        return;
+    }
+    structures.tick = clock.tick;
+
+    let mut points: Vec<Point> = q
+        .iter()
+        .map(|(e, b, t)| Point {
+            entity: e,
+            pos: t.translation.truncate(),
+            vel: b.vel,
+            mass: b.mass,
+        })
+        .collect();
+    points.sort_by_key(|p| p.entity);
+    let index: HashMap<Entity, usize> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.entity, i))
+        .collect();
+
+    // Friends of friends.
+    let (lo, hi) = points.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(lo, hi), p| (lo.min(p.pos), hi.max(p.pos)),
+    );
+    let extent = (hi - lo).max(Vec2::ONE);
+    let spacing = (extent.x * extent.y / points.len().max(1) as f32).sqrt();
+    let link = (spec.linking_factor * spacing).max(1.0);
+    hash.reset(link);
+    for p in &points {
+        hash.insert(p.entity, p.pos);
+    }
+    let mut parent: Vec<usize> = (0..points.len()).collect();
+    for i in 0..points.len() {
+        let pi = points[i].pos;
+        hash.for_each_near(pi, link, |e| {
+            let Some(&j) = index.get(&e) else {
+                return;
+            };
+            if j > i && (points[j].pos - pi).length_squared() <= link * link {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                if ri != rj {
+                    parent[ri.max(rj)] = ri.min(rj);
+                }
+            }
+        });
+    }
+    let mut clumps: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..points.len() {
+        let root = find(&mut parent, i);
+        clumps.entry(root).or_default().push(i);
+    }
+    let mut clumps: Vec<Vec<usize>> = clumps
+        .into_values()
+        .filter(|c| c.len() >= spec.min_members.max(2))
+        .collect();
+    clumps.sort_by_key(|c| (std::cmp::Reverse(c.len()), c[0]));
+
+    // Carry ids over: each old id goes to the new group holding most of its members.
+    let mut old_sizes: HashMap<u32, usize> = HashMap::new();
+    for id in structures.membership.values() {
+        *old_sizes.entry(*id).or_default() += 1;
+    }
+    let votes: Vec<HashMap<u32, usize>> = clumps
+        .iter()
+        .map(|c| {
+            let mut v: HashMap<u32, usize> = HashMap::new();
+            for &i in c {
+                if let Some(id) = structures.membership.get(&points[i].entity) {
+                    *v.entry(*id).or_default() += 1;
+                }
+            }
+            v
+        })
+        .collect();
+    let mut claims: Vec<(usize, u32, usize)> = votes
+        .iter()
+        .enumerate()
+        .flat_map(|(g, v)| v.iter().map(move |(id, n)| (g, *id, *n)))
+        .collect();
+    claims.sort_by(|a, b| b.2.cmp(&a.2).then(a.1.cmp(&b.1)));
+    let mut ids: Vec<Option<u32>> = vec![None; clumps.len()];
+    let mut taken: Vec<u32> = Vec::new();
+    for (g, id, _) in claims {
+        if ids[g].is_none() && !taken.contains(&id) {
+            ids[g] = Some(id);
+            taken.push(id);
+        }
+    }
+
+    let mut groups = Vec::with_capacity(clumps.len());
+    let mut membership = HashMap::new();
+    for (g, clump) in clumps.iter().enumerate() {
+        let id = match ids[g] {
+            Some(id) => id,
+            None => structures.fresh_id(),
+        };
+        let mass: f32 = clump.iter().map(|&i| points[i].mass).sum();
+        let com = clump
+            .iter()
+            .map(|&i| points[i].pos * points[i].mass)
+            .sum::<Vec2>()
+            / mass;
+        let vel = clump
+            .iter()
+            .map(|&i| points[i].vel * points[i].mass)
+            .sum::<Vec2>()
+            / mass;
+        let radii: Vec<f32> = clump
+            .iter()
+            .map(|&i| (points[i].pos - com).length())
+            .collect();
+        let mean_radius = clump
+            .iter()
+            .zip(&radii)
+            .map(|(&i, r)| r * points[i].mass)
+            .sum::<f32>()
+            / mass;
+        let spread = (radii.iter().map(|r| (r - mean_radius).powi(2)).sum::<f32>()
+            / radii.len() as f32)
+            .sqrt();
+        // A ring keeps its members in a thin annulus far wider than the linking length.
+        let shape = if mean_radius > 4.0 * link && spread < 0.25 * mean_radius {
+            GroupShape::Ring
+        } else {
+            GroupShape::Clump
+        };
+        let mut merged_from: Vec<u32> = votes[g]
+            .iter()
+            .filter(|(old, n)| {
+                **old != id && **n * 2 >= old_sizes.get(old).copied().unwrap_or(usize::MAX)
+            })
+            .map(|(old, _)| *old)
+            .collect();
+        merged_from.sort();
+        let members: Vec<Entity> = clump.iter().map(|&i| points[i].entity).collect();
+        for &e in &members {
+            membership.insert(e, id);
+        }
+        groups.push(Group {
+            id,
+            members,
+            mass,
+            com,
+            vel,
+            mean_radius,
+            shape,
+            merged_from,
+        });
+    }
+    structures.groups = groups;
+    structures.membership = membership;
+
+    // Bound subsystems from the orbit hierarchy.
+    let mut children: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for (e, orbit) in orbits.iter() {
+        if orbit.kind.is_bound() && index.contains_key(&e) {
+            children.entry(orbit.attractor).or_default().push(e);
+        }
+    }
+    // Sorted so mass sums do not depend on hash order.
+    for c in children.values_mut() {
+        c.sort();
+    }
+    let mut primaries: Vec<Entity> = children
+        .keys()
+        .copied()
+        .filter(|p| index.contains_key(p))
+        .collect();
+    primaries.sort();
+    structures
+        .subsystem_ids
+        .retain(|p, _| children.contains_key(p));
+    let mut subsystems = Vec::with_capacity(primaries.len());
+    for primary in primaries {
+        let (mut mass, mut moment, mut momentum) = (0.0, Vec2::ZERO, Vec2::ZERO);
+        let mut stack = vec![primary];
+        let mut depth = 0;
+        while let Some(e) = stack.pop() {
+            // Guards against a cycle if attractors swapped between frames.
+            depth += 1;
+            if depth > points.len() {
+                break;
+            }
+            let p = points[index[&e]];
+            mass += p.mass;
+            moment += p.pos * p.mass;
+            momentum += p.vel * p.mass;
+            if let Some(c) = children.get(&e) {
+                stack.extend(c.iter().filter(|c| index.contains_key(c)));
+            }
+        }
+        let id = match structures.subsystem_ids.get(&primary) {
+            Some(&id) => id,
+            None => {
+                let id = structures.fresh_id();
+                structures.subsystem_ids.insert(primary, id);
+                id
+            }
+        };
+        let satellites = children[&primary].clone();
+        subsystems.push(Subsystem {
+            id,
+            primary,
+            primary_class: Class::from_mass(points[index[&primary]].mass),
+            satellites,
+            mass,
+            com: moment / mass,
+            vel: momentum / mass,
+        });
+    }
+    structures.subsystems = subsystems;
+}
@@ -0,0 +1,255 @@
+//! The replay viewer.
+//!
+//! In `AppState::Viewing` a replay plays back under the viewer's own
+//! transport: pause, speed (replay ticks per frame), single steps and seeking
+//! on a timeline. The world only ever advances by re-executing the replay;
+//! the player's controls are off and the camera is free.
+//!
+//! Every [`KEYFRAME_EVERY`] ticks the viewer keeps a snapshot together with
+//! the playback position. Seeking backwards restores the last keyframe at or
+//! before the target, or restarts the replay when there is none, and
+//! re-simulates from there; seeking forwards re-simulates up to
+//! [`SEEK_TICKS_PER_FRAME`] ticks a frame. Notable events (merges, class
+//! changes, deaths) become timeline markers as the ticks they happen on are
+//! simulated.
+
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+
+use super::post_newtonian::BlackHoleMerger;
+use super::replay::{Playback, PlaybackPosition, Replay, ReplayCommand};
+use super::snapshot::{self, Snapshot, SnapshotStatus};
+use super::{Body, BodyAbsorbed, BodyId, Class, Player, PlayerDied};
+
+pub const KEYFRAME_EVERY: u64 = 60;
+
+pub const SEEK_TICKS_PER_FRAME: u64 = 240;
+
+/// Playback speeds offered, in replay ticks per frame.
+pub const SPEEDS: [f32; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum MarkerKind {
+    Merge,
+    ClassChange,
+    Death,
+}
+
+#[derive(Resource)]
+pub struct Viewer {
+    pub paused: bool,
+    /// Replay ticks per frame; below 1, some frames run none.
+    pub speed: f32,
+    /// Single ticks to run while paused.
+    pub steps: u32,
+    /// Playback tick to jump to.
+    pub seek: Option<u64>,
+    /// Fractional ticks owed at speeds below 1.
+    owed: f32,
+    keyframes: BTreeMap<u64, (PlaybackPosition, Snapshot)>,
+    /// Keyed by playback tick, kind and the body involved, so re-simulating a
+    /// stretch does not add them twice.
+    markers: BTreeMap<(u64, MarkerKind, u64), String>,
+}
+
+impl Default for Viewer {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed: 1.0,
+            steps: 0,
+            seek: None,
+            owed: 0.0,
+            keyframes: BTreeMap::new(),
+            markers: BTreeMap::new(),
+        }
+    }
+}
+
+impl Viewer {
+    /// Markers found so far, in tick order.
+    pub fn markers(&self) -> impl Iterator<Item = (u64, MarkerKind, &str)> + '_ {
+        self.markers
+            .iter()
+            .map(|(&(tick, kind, _), label)| (tick, kind, label.as_str()))
+    }
+
+    pub fn marker_count(&self) -> usize {
+        self.markers.len()
+    }
+}
+
+/// Clears the viewer on the way in and out, keeping the chosen speed.
+pub(super) fn reset(mut viewer: ResMut<Viewer>) {
+    *viewer = Viewer {
+        speed: viewer.speed,
+        ..default()
+    };
+}
+
+/// How many ticks the viewer wants this frame, never past the end of the
+/// replay. Pauses once the end is reached.
+pub(super) fn ticks_this_frame(world: &mut World) -> u32 {
+    let Some((tick, end)) = world
+        .resource::<Replay>()
+        .playback()
+        .map(|pb| (pb.tick, pb.header().ticks))
+    else {
+        return 0;
+    };
+    let mut viewer = world.resource_mut::<Viewer>();
+    let remaining = end.saturating_sub(tick);
+    let wanted = match viewer.seek {
+        Some(target) if target > tick => (target - tick).min(SEEK_TICKS_PER_FRAME),
+        Some(_) => {
+            viewer.seek = None;
+            0
+        }
+        None if viewer.paused => u64::from(std::mem::take(&mut viewer.steps)),
+        None => {
+            viewer.owed += viewer.speed;
+            let whole = viewer.owed.floor();
+            viewer.owed -= whole;
+            whole as u64
+        }
+    };
+    if wanted >= remaining && viewer.seek.is_none() {
+        viewer.paused = true;
+    }
+    wanted.min(remaining) as u32
+}
+
+/// Moves the playback back to the keyframe nearest a pending seek target,
+/// when that saves re-simulating, and restarts the replay when seeking before
+/// the first one. The remaining ticks are run by `ticks_this_frame`.
+pub(super) fn seek(world: &mut World) {
+    let Some(target) = world.resource::<Viewer>().seek else {
+        return;
+    };
+    let Some((tick, end, path)) = world
+        .resource::<Replay>()
+        .playback()
+        .map(|pb| (pb.tick, pb.header().ticks, pb.path.clone()))
+    else {
+        world.resource_mut::<Viewer>().seek = None;
+        return;
+    };
+    let target = target.min(end);
+    world.resource_mut::<Viewer>().seek = Some(target);
+    if target == tick {
+        return;
+    }
+    let from = world
+        .resource::<Viewer>()
+        .keyframes
+        .range(..=target)
+        .next_back()
+        .map(|(&k, _)| k)
+        .filter(|&k| target < tick || k > tick);
+    match from {
+        Some(k) => world.resource_scope(|world, viewer: Mut<Viewer>| {
+            let (position, snapshot) = &viewer.keyframes[&k];
+            if let Err(e) = snapshot::restore(world, snapshot) {
+                world.resource_mut::<SnapshotStatus>().message = Some(format!("Viewer: {e}"));
+            }
+            if let Some(pb) = world.resource_mut::<Replay>().playback_mut() {
+                pb.seek(position);
+            }
+        }),
+        None if target < tick => {
+            world.send_event(ReplayCommand::View(path));
+        }
+        None => {}
+    }
+}
+
+/// Keeps a keyframe every [`KEYFRAME_EVERY`] playback ticks.
+pub(super) fn record_keyframe(world: &mut World) {
+    let Some(position) = world
+        .resource::<Replay>()
+        .playback()
+        .map(Playback::position)
+    else {
+        return;
+    };
+    if !position.tick.is_multiple_of(KEYFRAME_EVERY)
+        || world
+            .resource::<Viewer>()
+            .keyframes
+            .contains_key(&position.tick)
+    {
+        return;
+    }
+    let snapshot = snapshot::capture(world);
+    world
+        .resource_mut::<Viewer>()
+        .keyframes
+        .insert(position.tick, (position, snapshot));
+}
+
+/// Stars and black holes as of the previous tick, to spot promotions.
+#[derive(Default)]
+pub(super) struct ClassWatch {
+    tick: u64,
+    heavy: HashSet<(BodyId, Class)>,
+}
+
+/// Turns this tick's merges, class changes and deaths into markers.
+pub(super) fn collect_markers(
+    mut viewer: ResMut<Viewer>,
+    replay: Res<Replay>,
+    mut ev_absorbed: EventReader<BodyAbsorbed>,
+    mut ev_merger: EventReader<BlackHoleMerger>,
+    mut ev_died: EventReader<PlayerDied>,
+    bodies: Query<(&BodyId, &Body, Has<Player>)>,
+    ids: Query<&BodyId>,
+    mut watch: Local<ClassWatch>,
+) {
+    let Some(tick) = replay.playback().map(|pb| pb.tick) else {
+        return;
+    };
+    let id_of = |e: Entity| ids.get(e).map_or(u64::MAX, |id| id.0);
+    let markers = &mut viewer.markers;
+
+    for ev in ev_absorbed.read() {
+        if ev.loser_class != Class::Asteroid {
+            let winner = id_of(ev.winner);
+            markers.insert(
+                (tick, MarkerKind::Merge, winner),
+                format!("{:?} absorbed by body {winner}", ev.loser_class),
+            );
+        }
+    }
+    for ev in ev_merger.read() {
+        markers.insert(
+            (tick, MarkerKind::Merge, id_of(ev.remnant)),
+            format!("Black hole merger ({:.0} mass)", ev.remnant_mass),
+        );
+    }
+    if ev_died.read().count() > 0 {
+        markers.insert((tick, MarkerKind::Death, 0), "Player died".into());
+    }
+
+    // Only compare against the tick just before; after a seek the old set
+    // describes another point in the run. Playback ticks start at 1.
+    let consecutive = watch.tick > 0 && watch.tick + 1 == tick;
+    let mut heavy = HashSet::with_capacity(watch.heavy.len());
+    for (id, body, player) in &bodies {
+        if !player && !matches!(body.class, Class::Star | Class::BlackHole) {
+            continue;
+        }
+        heavy.insert((*id, body.class));
+        if consecutive && !watch.heavy.contains(&(*id, body.class)) {
+            let who = if player {
+                "Player".to_string()
+            } else {
+                format!("Body {}", id.0)
+            };
+            markers.insert(
+                (tick, MarkerKind::ClassChange, id.0),
+                format!("{who} became {:?}", body.class),
+            );
+        }
+    }
+    *watch = ClassWatch { tick, heavy };
+}
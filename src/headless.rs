@@ -0,0 +1,342 @@
+//! Headless determinism checker.
+//!
+//! `solar2-rs --check-determinism [--scenario NAME] [--seed N] [--ticks N]
+//! [--parallel]` runs the scenario in two child processes, compares their
+//! per-tick state hashes and, at the first tick where they differ, reruns both
+//! up to that tick and lists the bodies whose state differs. The exit code is
+//! 0 if the runs agree, 1 if they diverge and 2 on bad arguments or a failed
+//! run, including one whose clock stopped before the requested tick count.
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use std::collections::BTreeMap;
+use std::process::{Command, Stdio};
+
+use crate::domain::simulation::autosave::Autosave;
+use crate::domain::simulation::state_hash::{body_records, BodyRecord, StateHash};
+use crate::domain::simulation::{BodyId, Class, Scenario, SimSettings};
+use crate::domain::{AppState, SimPlugin, SimState};
+
+const CHECK_FLAG: &str = "--check-determinism";
+const CHILD_FLAG: &str = "--determinism-run";
+/// Bodies listed at a divergence before the report is cut short.
+const MAX_REPORTED: usize = 20;
+
+#[derive(Clone, Debug)]
+struct Options {
+    scenario: Scenario,
+    scenario_name: String,
+    seed: u64,
+    ticks: Option<u64>,
+    parallel: bool,
+    dump_at: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scenario: Scenario::CalmBelts,
+            scenario_name: "calm-belts".into(),
+            seed: 1,
+            ticks: None,
+            parallel: false,
+            dump_at: None,
+        }
+    }
+}
+
+fn parse_scenario(name: &str) -> Option<Scenario> {
+    Some(match name {
+        "calm-belts" => Scenario::CalmBelts,
+        "binary-mayhem" => Scenario::BinaryMayhem,
+        "star-nursery" => Scenario::StarNursery,
+        "bh-arena" => Scenario::BHArena,
+        "spiral-galaxy" => Scenario::SpiralGalaxy,
+        _ => return None,
+    })
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options::default();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| {
+            it.next()
+                .cloned()
+                .ok_or_else(|| format!("{name} needs a value"))
+        };
+        match arg.as_str() {
+            CHECK_FLAG | CHILD_FLAG => {}
+            "--scenario" => {
+                let name = value(arg)?;
+                opts.scenario =
+                    parse_scenario(&name).ok_or_else(|| format!("unknown scenario `{name}`"))?;
+                opts.scenario_name = name;
+            }
+            "--seed" => opts.seed = value(arg)?.parse().map_err(|e| format!("--seed: {e}"))?,
+            "--ticks" => {
+                opts.ticks = Some(value(arg)?.parse().map_err(|e| format!("--ticks: {e}"))?)
+            }
+            "--parallel" => opts.parallel = true,
+            "--dump-at" => {
+                opts.dump_at = Some(value(arg)?.parse().map_err(|e| format!("--dump-at: {e}"))?)
+            }
+            other => return Err(format!("unknown argument `{other}`")),
+        }
+    }
+    Ok(opts)
+}
+
+/// Handles a headless invocation; returns the process exit code, or `None`
+/// when the arguments ask for the normal windowed game.
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let child = args.iter().any(|a| a == CHILD_FLAG);
+    if !child && !args.iter().any(|a| a == CHECK_FLAG) {
+        return None;
+    }
+    let opts = match parse(&args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return Some(2);
+        }
+    };
+    Some(if child {
+        run_child(&opts)
+    } else {
+        check(&opts)
+    })
+}
+
+fn settings_for(opts: &Options) -> SimSettings {
+    let mut settings = SimSettings::from_scenario(opts.scenario);
+    settings.deterministic = true;
+    settings.seed = opts.seed;
+    settings
+}
+
+/// Ticks a run steps when `--ticks` is not given: a minute of sim time.
+fn requested_ticks(opts: &Options) -> u64 {
+    opts.ticks
+        .unwrap_or_else(|| (60.0 / settings_for(opts).dt).ceil() as u64)
+}
+
+/// Steps the scenario, printing `tick <n> <hash>` after every tick and the
+/// body records at `dump_at`.
+fn run_child(opts: &Options) -> i32 {
+    let settings = settings_for(opts);
+    let ticks = opts.dump_at.unwrap_or_else(|| requested_ticks(opts));
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(StatesPlugin)
+        .insert_state(if opts.parallel {
+            SimState::Parallel
+        } else {
+            SimState::Sequential
+        })
+        .init_state::<AppState>()
+        .add_plugins(SimPlugin::default())
+        .insert_resource(settings);
+    // Checker runs must not touch the player's autosaves.
+    app.world_mut().resource_mut::<Autosave>().enabled = false;
+    app.finish();
+    app.cleanup();
+
+    for _ in 0..ticks {
+        app.update();
+        let hash = *app.world().resource::<StateHash>();
+        println!("tick {} {:016x}", hash.tick, hash.value);
+        if opts.dump_at == Some(hash.tick) {
+            for r in body_records(app.world_mut()) {
+                println!(
+                    "body {} {:08x} {:08x} {:08x} {:08x} {:08x} {}",
+                    r.id.0,
+                    r.pos.x.to_bits(),
+                    r.pos.y.to_bits(),
+                    r.vel.x.to_bits(),
+                    r.vel.y.to_bits(),
+                    r.mass.to_bits(),
+                    r.class as u32
+                );
+            }
+            break;
+        }
+    }
+    0
+}
+
+#[derive(Default)]
+struct ChildOutput {
+    hashes: BTreeMap<u64, u64>,
+    bodies: Vec<BodyRecord>,
+}
+
+fn parse_class(n: u32) -> Option<Class> {
+    [
+        Class::Asteroid,
+        Class::Planet,
+        Class::Star,
+        Class::BlackHole,
+    ]
+    .get(n as usize)
+    .copied()
+}
+
+fn parse_child(stdout: &str) -> Option<ChildOutput> {
+    let mut out = ChildOutput::default();
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[..] {
+            ["tick", tick, hash] => {
+                out.hashes
+                    .insert(tick.parse().ok()?, u64::from_str_radix(hash, 16).ok()?);
+            }
+            ["body", id, px, py, vx, vy, mass, class] => {
+                let bits = |s: &str| u32::from_str_radix(s, 16).ok().map(f32::from_bits);
+                out.bodies.push(BodyRecord {
+                    id: BodyId(id.parse().ok()?),
+                    pos: Vec2::new(bits(px)?, bits(py)?),
+                    vel: Vec2::new(bits(vx)?, bits(vy)?),
+                    mass: bits(mass)?,
+                    class: parse_class(class.parse().ok()?)?,
+                });
+            }
+            _ => {}
+        }
+    }
+    Some(out)
+}
+
+fn spawn_runs(opts: &Options, dump_at: Option<u64>) -> Result<[ChildOutput; 2], String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let children = [(); 2].map(|()| {
+        let mut cmd = Command::new(&exe);
+        cmd.arg(CHILD_FLAG)
+            .args(["--scenario", &opts.scenario_name])
+            .args(["--seed", &opts.seed.to_string()]);
+        if let Some(t) = opts.ticks {
+            cmd.args(["--ticks", &t.to_string()]);
+        }
+        if opts.parallel {
+            cmd.arg("--parallel");
+        }
+        if let Some(t) = dump_at {
+            cmd.args(["--dump-at", &t.to_string()]);
+        }
+        cmd.stdout(Stdio::piped()).stderr(Stdio::inherit()).spawn()
+    });
+    // Both runs go at once; each is drained on its own thread so neither
+    // stalls on a full pipe.
+    let [a, b] = std::thread::scope(|scope| {
+        children
+            .map(|child| {
+                scope.spawn(move || -> Result<ChildOutput, String> {
+                    let out = child
+                        .and_then(|c| c.wait_with_output())
+                        .map_err(|e| e.to_string())?;
+                    if !out.status.success() {
+                        return Err(format!("run exited with {}", out.status));
+                    }
+                    let text = String::from_utf8_lossy(&out.stdout);
+                    parse_child(&text).ok_or_else(|| "unreadable run output".to_string())
+                })
+            })
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|_| Err("run thread panicked".into()))
+            })
+    });
+    Ok([a?, b?])
+}
+
+fn check(opts: &Options) -> i32 {
+    println!(
+        "Checking `{}` (seed {}, {} schedule)",
+        opts.scenario_name,
+        opts.seed,
+        if opts.parallel {
+            "parallel"
+        } else {
+            "sequential"
+        }
+    );
+    let [a, b] = match spawn_runs(opts, None) {
+        Ok(runs) => runs,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return 2;
+        }
+    };
+    let divergence = a
+        .hashes
+        .iter()
+        .find(|(tick, hash)| b.hashes.get(tick).is_some_and(|h| h != *hash))
+        .map(|(tick, _)| *tick);
+    let Some(tick) = divergence else {
+        if a.hashes.len() != b.hashes.len() {
+            println!(
+                "Runs stopped at different ticks ({} vs {}) with matching hashes up to then",
+                a.hashes.len(),
+                b.hashes.len()
+            );
+            return 1;
+        }
+        // Repeated ticks collapse into one entry, so a stalled clock shows up here.
+        let requested = requested_ticks(opts);
+        if (a.hashes.len() as u64) < requested {
+            eprintln!(
+                "error: the clock only reached {} distinct ticks of the {requested} requested",
+                a.hashes.len()
+            );
+            return 2;
+        }
+        let (last_tick, last_hash) = a.hashes.iter().next_back().unwrap_or((&0, &0));
+        println!("Identical over {last_tick} ticks (final hash {last_hash:016x})");
+        return 0;
+    };
+
+    println!(
+        "First divergence at tick {tick}: {:016x} vs {:016x}",
+        a.hashes[&tick], b.hashes[&tick]
+    );
+    let [a, b] = match spawn_runs(opts, Some(tick)) {
+        Ok(runs) => runs,
+        Err(e) => {
+            eprintln!("error while collecting bodies: {e}");
+            return 1;
+        }
+    };
+    let left: BTreeMap<BodyId, BodyRecord> = a.bodies.iter().map(|r| (r.id, *r)).collect();
+    let right: BTreeMap<BodyId, BodyRecord> = b.bodies.iter().map(|r| (r.id, *r)).collect();
+    let mut ids: Vec<BodyId> = left.keys().chain(right.keys()).copied().collect();
+    ids.sort();
+    ids.dedup();
+    let differing: Vec<BodyId> = ids
+        .into_iter()
+        .filter(|id| match (left.get(id), right.get(id)) {
+            (Some(l), Some(r)) => !l.same_bits(r),
+            _ => true,
+        })
+        .collect();
+    if differing.is_empty() {
+        println!("Bodies agree; the difference is in the RNG streams or settings");
+    } else {
+        println!("{} bodies differ:", differing.len());
+    }
+    for id in differing.iter().take(MAX_REPORTED) {
+        match (left.get(id), right.get(id)) {
+            (Some(l), Some(r)) => println!(
+                "  body {}: pos {} vs {}, vel {} vs {}, mass {} vs {}",
+                id.0, l.pos, r.pos, l.vel, r.vel, l.mass, r.mass
+            ),
+            (Some(_), None) => println!("  body {}: only in run A", id.0),
+            _ => println!("  body {}: only in run B", id.0),
+        }
+    }
+    if differing.len() > MAX_REPORTED {
+        println!("  ... and {} more", differing.len() - MAX_REPORTED);
+    }
+    1
+}
@@ -1,4 +1,8 @@
+// Bevy systems routinely take many params and nested query tuples.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
 mod domain;
+mod headless;
 
 use bevy::core_pipeline::bloom::BloomSettings;
 use bevy::core_pipeline::tonemapping::Tonemapping;
@@ -7,6 +11,9 @@ use bevy::prelude::*;
 use domain::{InputPlugin, SimPlugin, UiPlugin};
 
 fn main() {
+    if let Some(code) = headless::run_from_args() {
+        std::process::exit(code);
+    }
     App::new()
         .insert_resource(ClearColor(Color::srgb(0.02, 0.02, 0.04)))
         .insert_resource(Msaa::Sample4)
@@ -22,7 +29,7 @@ fn main() {
         }))
         .init_state::<domain::SimState>()
         .init_state::<domain::AppState>()
-        .add_plugins((SimPlugin, UiPlugin, InputPlugin))
+        .add_plugins((SimPlugin::default(), UiPlugin, InputPlugin))
         .add_systems(Startup, setup_camera)
         .run();
 }